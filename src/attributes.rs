@@ -11,9 +11,17 @@
 //! ```
 //! (Most of the time the safe way (`Identity::id`) should be good, but as we didn't provide a type for something like the
 //! [`lblString`](https://graphviz.org/doc/info/attrs.html#k:lblString), you may want to add a unquoted string using the *unsafe* way.)
-use std::hint::unreachable_unchecked;
+//!
+//! Counts that graphviz requires to be non-negative (`levels`, `maxiter`, `minlen`, `peripheries`,
+//! `samplepoints`, `sides` and `sortv`) take a `u32`, so a negative value does not compile instead
+//! of being rejected by graphviz. `peripheries(0)` is the usual way to draw a node without border.
+//! ```compile_fail
+//! use tabbycat::attributes::peripheries;
+//! let border = peripheries(-1);
+//! ```
+use std::convert::TryFrom;
 
-use crate::{AttrPair, Identity};
+use crate::{AttrPair, Error, Identity, StmtList, SubGraph};
 
 macro_rules! attribute_from {
         ($id:ident, $t:ty) => {
@@ -23,6 +31,14 @@ macro_rules! attribute_from {
         };
     }
 
+macro_rules! attribute_into {
+        ($id:ident, $t:ty) => {
+            pub fn $id<'a, T: Into<$t>>(value: T) -> AttrPair<'a> {
+                (Identity::String(stringify!($id)), Identity::from(value.into()))
+            }
+        };
+    }
+
 macro_rules! attribute_quoted {
         ($id:ident) => {
             pub fn $id<'a>(value: &'a str) -> AttrPair<'a> {
@@ -57,11 +73,17 @@ attribute_quoted!(edgehref);
 attribute_quoted!(edgetarget);
 attribute_quoted!(edgetooltip);
 attribute_from!(epsilon, f64);
+attribute_from!(fixedsize, bool);
 attribute_quoted!(fontname);
 attribute_quoted!(fontnames);
 attribute_quoted!(fontpath);
 attribute_from!(fontsize, f64);
-attribute_from!(forcelables, bool);
+attribute_from!(forcelabels, bool);
+/// Misspelled `forcelabels`, which graphviz does not know; it now writes `forcelabels`.
+#[deprecated(note = "use `forcelabels`")]
+pub fn forcelables<'a>(value: bool) -> AttrPair<'a> {
+    forcelabels(value)
+}
 attribute_from!(gradientangle, i32);
 attribute_quoted!(group);
 attribute_quoted!(headURL);
@@ -92,21 +114,23 @@ attribute_quoted!(labelloc);
 attribute_quoted!(labeltarget);
 attribute_quoted!(labeltooltip);
 attribute_from!(landscape, bool);
+attribute_quoted!(layer);
 attribute_quoted!(layerlistsep);
+attribute_quoted!(layerselect);
 attribute_quoted!(layersep);
 attribute_quoted!(layout);
 attribute_from!(len, f64);
-attribute_from!(levels, i32);
+attribute_from!(levels, u32);
 attribute_from!(levelsgap, f64);
 attribute_quoted!(lhead);
 attribute_from!(lheight, f64);
 attribute_quoted!(ltail);
 attribute_from!(lwidth, f64);
 attribute_from!(margin, f64);
-attribute_from!(maxiter, i32);
+attribute_from!(maxiter, u32);
 attribute_from!(mclimit, f64);
 attribute_from!(mindist, f64);
-attribute_from!(minlen, i32);
+attribute_from!(minlen, u32);
 attribute_quoted!(mode);
 attribute_quoted!(model);
 attribute_from!(mosek, bool);
@@ -122,13 +146,13 @@ attribute_from!(orientation, f64);
 attribute_from!(overlap_scaling, f64);
 attribute_from!(overlap_shrink, bool);
 attribute_from!(pad, f64);
-attribute_from!(page, f64);
+attribute_into!(page, PageSize);
 attribute_from!(penwidth, f64);
-attribute_from!(peripheries, i32);
+attribute_from!(peripheries, u32);
 attribute_from!(pin, bool);
 attribute_from!(quantum, f64);
 attribute_from!(ranksep, f64);
-attribute_from!(ratio, f64);
+attribute_into!(ratio, Ratio);
 attribute_from!(regular, bool);
 attribute_from!(remincross, bool);
 attribute_from!(repulsiveforce, f64);
@@ -138,15 +162,15 @@ attribute_from!(rotate, i32);
 attribute_from!(rotation, f64);
 attribute_quoted!(samehead);
 attribute_quoted!(sametail);
-attribute_from!(samplepoints, i32);
+attribute_from!(samplepoints, u32);
 attribute_from!(scale, f64);
 attribute_from!(searchsize, i32);
 attribute_quoted!(shapefile);
 attribute_from!(showboxes, i32);
-attribute_from!(sides, i32);
-attribute_from!(size, f64);
+attribute_from!(sides, u32);
+attribute_into!(size, Size);
 attribute_from!(skew, f64);
-attribute_from!(sortv, i32);
+attribute_from!(sortv, u32);
 attribute_quoted!(stylesheet);
 attribute_quoted!(tailURL);
 attribute_from!(tailclip, bool);
@@ -163,9 +187,10 @@ attribute_from!(width, f64);
 attribute_quoted!(xdotversion);
 attribute_quoted!(xlabel);
 attribute_from!(z, f64);
-attribute_from!(bgcolor, Color);
-attribute_from!(color, Color);
-attribute_from!(fillcolor, Color);
+attribute_into!(bgcolor, ColorList);
+attribute_into!(color, ColorList);
+attribute_into!(fillcolor, ColorList);
+attribute_from!(fontcolor, Color);
 attribute_from!(labelfontcolor, Color);
 attribute_from!(pencolor, Color);
 attribute_from!(shape, Shape);
@@ -175,6 +200,7 @@ attribute_from!(smoothing, SmoothType);
 attribute_from!(rankdir, RankDir);
 attribute_from!(rank, RankType);
 attribute_from!(quadtree, QuadType);
+attribute_from!(splines, Splines);
 attribute_from!(pagedir, PageDir);
 attribute_from!(clusterrank, ClusterMode);
 attribute_from!(outputorder, OutputMode);
@@ -183,6 +209,113 @@ attribute_from!(lp, Point);
 attribute_from!(pos, Point);
 attribute_from!(tail_lp, Point);
 attribute_from!(xlp, Point);
+/// Make a node exactly `width` by `height` inches. Without `fixedsize=true` graphviz only treats
+/// `width` and `height` as minimums, so the three pairs are returned together.
+pub fn fixed_size<'a>(width_inches: f64, height_inches: f64) -> Vec<AttrPair<'a>> {
+    vec![fixedsize(true), width(width_inches), height(height_inches)]
+}
+/// A `shape=polygon` node with `sides` sides drawn with `peripheries` outlines. `distortion`,
+/// `skew` and `orientation` can be added on top to shape the polygon further.
+pub fn polygon<'a>(sides_count: u32, peripheries_count: u32) -> Vec<AttrPair<'a>> {
+    vec![shape(Shape::Polygon), sides(sides_count), peripheries(peripheries_count)]
+}
+/// Highlight a node or edge: draw it in `value` with a pen `width` points wide, in bold style.
+pub fn emphasize<'a>(value: Color, width: f64) -> Vec<AttrPair<'a>> {
+    vec![color(value), penwidth(width), style(Style::Bold)]
+}
+/// Draw the outline of a node or cluster, or an edge, in `value` with a pen `width` points wide.
+/// The width must be finite and not negative, `0` hides the outline.
+/// ```
+/// use tabbycat::attributes::*;
+/// use tabbycat::{AttrList, Error};
+/// assert_eq!("[color=blue,penwidth=2]", AttrList::new().extend(stroke(Color::Blue, 2.0).unwrap()).to_string());
+/// assert_eq!(Err(Error::NegativeNumber(-1.0)), stroke(Color::Blue, -1.0));
+/// ```
+pub fn stroke<'a>(value: Color, width: f64) -> Result<Vec<AttrPair<'a>>, Error> {
+    if !width.is_finite() {
+        Err(Error::NonFiniteNumber(width))
+    } else if width < 0.0 {
+        Err(Error::NegativeNumber(width))
+    } else {
+        Ok(vec![color(value), penwidth(width)])
+    }
+}
+/// Control how an edge takes part in ranking: a higher `weight` pulls its nodes closer together
+/// and keeps the edge shorter and straighter, while `constrain=false` leaves the edge out of ranking.
+pub fn rank_constraint<'a>(weight_value: f64, constrain: bool) -> Vec<AttrPair<'a>> {
+    vec![weight(weight_value), constraint(constrain)]
+}
+/// The separation between ranks written as any form of `Ranksep`, where `ranksep` only takes a
+/// plain distance. A distance that is negative or not finite and an empty `Ranksep::List` are errors.
+/// ```
+/// use tabbycat::attributes::*;
+/// use tabbycat::{AttrList, Error};
+/// assert_eq!(r#"[ranksep="1 equally"]"#, AttrList::new().add_pair(rank_separation(Ranksep::Equally(1.0)).unwrap()).to_string());
+/// assert_eq!(Err(Error::NegativeNumber(-1.0)), rank_separation(Ranksep::Value(-1.0)));
+/// assert_eq!(Err(Error::EmptyList), rank_separation(Ranksep::List(vec![])));
+/// ```
+pub fn rank_separation<'a>(value: Ranksep) -> Result<AttrPair<'a>, Error> {
+    Ok((Identity::String("ranksep"), Identity::try_from(value)?))
+}
+/// Set the graph spacing in inches: `node` between nodes of the same rank (`nodesep`) and `rank`
+/// between ranks (`ranksep`), e.g. small values for a compact layout.
+pub fn spacing<'a>(node: f64, rank: f64) -> Vec<AttrPair<'a>> {
+    vec![nodesep(node), ranksep(rank)]
+}
+/// Like `spacing`, but with `ranksep="<rank> equally"`, so all ranks are the same distance apart.
+/// ```
+/// use tabbycat::attributes::*;
+/// use tabbycat::AttrList;
+/// assert_eq!(r#"[nodesep=0.25,ranksep="0.5 equally"]"#, AttrList::new().extend(spacing_equally(0.25, 0.5)).to_string());
+/// ```
+pub fn spacing_equally<'a>(node: f64, rank: f64) -> Vec<AttrPair<'a>> {
+    vec![nodesep(node), (Identity::String("ranksep"), Ranksep::Equally(rank).render())]
+}
+/// Keep the nodes of the force-directed layouts (`neato`, `fdp`, `sfdp`) apart: `overlap=false`
+/// removes overlaps, leaving at least `margin` points around nodes (`sep`) and around the splines
+/// routed between them (`esep`). The `+` makes graphviz read the margin as additive points
+/// instead of a scale factor. A margin that is negative or not finite is an error.
+/// ```
+/// use tabbycat::{AttrList, Error};
+/// use tabbycat::attributes::no_overlap;
+/// assert_eq!("[overlap=false,sep=\"+4\",esep=\"+4\"]", AttrList::new().extend(no_overlap(4.0).unwrap()).to_string());
+/// assert_eq!(Err(Error::NegativeNumber(-4.0)), no_overlap(-4.0));
+/// ```
+pub fn no_overlap<'a>(margin: f64) -> Result<Vec<AttrPair<'a>>, Error> {
+    if !margin.is_finite() {
+        Err(Error::NonFiniteNumber(margin))
+    } else if margin < 0.0 {
+        Err(Error::NegativeNumber(margin))
+    } else {
+        let value = format!("+{}", Identity::Double(margin));
+        Ok(vec![
+            (Identity::String("overlap"), Identity::Bool(false)),
+            (Identity::String("sep"), Identity::OwnedQuoted(value.clone())),
+            (Identity::String("esep"), Identity::OwnedQuoted(value)),
+        ])
+    }
+}
+/// Pack a `circo` layout tighter by halving the minimum distance between nodes (`mindist`,
+/// default 1.0).
+pub fn circo_compact<'a>() -> Vec<AttrPair<'a>> {
+    vec![mindist(0.5)]
+}
+/// Spread an `fdp` layout out by doubling the ideal edge length (`K`, default 0.3) and, for
+/// `sfdp`, the repulsive force between nodes (`repulsiveforce`, default 1.0).
+/// ```
+/// use tabbycat::AttrList;
+/// use tabbycat::attributes::fdp_spacious;
+/// assert_eq!("[K=0.6,repulsiveforce=2]", AttrList::new().extend(fdp_spacious()).to_string());
+/// ```
+pub fn fdp_spacious<'a>() -> Vec<AttrPair<'a>> {
+    vec![K(0.6), repulsiveforce(2.0)]
+}
+/// The graph attribute `compound=true`, which lets edges end at cluster borders through `lhead`
+/// and `ltail`. Without it graphviz ignores those silently; `Graph::validate` reports them.
+pub fn enable_cluster_edges<'a>() -> AttrPair<'a> {
+    compound(true)
+}
+
 pub fn arrowhead<'a>(value: ArrowShape) -> AttrPair<'a> {
     (Identity::String("arrowhead"), Identity::ArrowName([Some(arrow_str(value)), None, None, None]))
 }
@@ -214,6 +347,13 @@ pub fn arrowtail3<'a>(a: ArrowShape, b: ArrowShape, c: ArrowShape) -> AttrPair<'
 pub fn arrowtail4<'a>(a: ArrowShape, b: ArrowShape, c: ArrowShape, d: ArrowShape) -> AttrPair<'a> {
     (Identity::String("arrowtail"), Identity::ArrowName([Some(arrow_str(a)), Some(arrow_str(b)), Some(arrow_str(c)), Some(arrow_str(d))]))
 }
+
+/// Set both arrow shapes together with `dir=both`, without which graphviz does not draw the tail
+/// arrow of edges in a digraph.
+pub fn arrows<'a>(head: ArrowShape, tail: ArrowShape) -> Vec<AttrPair<'a>> {
+    vec![arrowhead(head), arrowtail(tail), dir(DirType::Both)]
+}
+
 /// Smoothing Method
 #[derive(Debug)]
 pub enum SmoothType {
@@ -281,6 +421,25 @@ impl<'a> From<RankType> for Identity<'a> {
     }
 }
 
+/// Shorthand for `RankType`, matching the attribute name `rank`
+pub type Rank = RankType;
+
+impl<'a> SubGraph<'a> {
+    /// Create an anonymous subgraph placing `nodes` under the given rank constraint:
+    /// ```
+    /// use tabbycat::{Identity, SubGraph};
+    /// use tabbycat::attributes::Rank;
+    /// let sub = SubGraph::with_rank(Rank::Same, vec![Identity::id("a").unwrap(), Identity::id("b").unwrap()]);
+    /// assert_eq!("{rank=same;a;b;}", sub.to_string());
+    /// ```
+    pub fn with_rank<I: IntoIterator<Item=Identity<'a>>>(rank: RankType, nodes: I) -> Self {
+        let stmts = nodes.into_iter()
+            .fold(StmtList::new().add_equation(Identity::String("rank"), Identity::from(rank)),
+                  |list, id| list.add_node(id, None, None));
+        SubGraph::cluster(stmts)
+    }
+}
+
 /// Quadtree Algorithm
 #[derive(Debug)]
 pub enum QuadType {
@@ -299,6 +458,203 @@ impl<'a> From<QuadType> for Identity<'a> {
     }
 }
 
+/// Edge routing, see the [document](https://graphviz.org/docs/attrs/splines/).
+/// `True` and `False` are written as booleans, which graphviz treats like `Spline` and `Line`.
+#[derive(Debug, Clone, Copy)]
+pub enum Splines {
+    None,
+    Line,
+    Polyline,
+    Curved,
+    Ortho,
+    Spline,
+    True,
+    False,
+}
+
+impl<'a> From<Splines> for Identity<'a> {
+    fn from(splines: Splines) -> Self {
+        match splines {
+            Splines::True => Identity::Bool(true),
+            Splines::False => Identity::Bool(false),
+            Splines::None => Identity::String("none"),
+            Splines::Line => Identity::String("line"),
+            Splines::Polyline => Identity::String("polyline"),
+            Splines::Curved => Identity::String("curved"),
+            Splines::Ortho => Identity::String("ortho"),
+            Splines::Spline => Identity::String("spline"),
+        }
+    }
+}
+
+impl From<bool> for Splines {
+    fn from(flag: bool) -> Self {
+        if flag { Splines::True } else { Splines::False }
+    }
+}
+
+/// The separation between ranks in inches, see [ranksep](https://graphviz.org/docs/attrs/ranksep/).
+/// A plain `f64` converts into `Ranksep::Value`. Converting into an `Identity` fails for negative
+/// or non-finite distances and for an empty list, see `rank_separation`.
+/// ```
+/// use tabbycat::attributes::*;
+/// use tabbycat::AttrList;
+/// let attrs = AttrList::new()
+///     .add_pair(ranksep(0.5))
+///     .add_pair(rank_separation(Ranksep::Equally(1.0)).unwrap())
+///     .add_pair(rank_separation(Ranksep::List(vec![0.5, 1.25])).unwrap());
+/// assert_eq!(r#"[ranksep=0.5,ranksep="1 equally",ranksep="0.5:1.25"]"#, attrs.to_string());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ranksep {
+    /// The minimum distance between ranks
+    Value(f64),
+    /// The same distance between all ranks, written as `"<n> equally"`
+    Equally(f64),
+    /// One distance per pair of adjacent ranks, the last one used for all remaining ranks
+    List(Vec<f64>),
+}
+
+impl From<f64> for Ranksep {
+    fn from(value: f64) -> Self {
+        Ranksep::Value(value)
+    }
+}
+
+impl Ranksep {
+    /// The identity for this separation, without checking the distances.
+    fn render<'a>(self) -> Identity<'a> {
+        match self {
+            Ranksep::Value(value) => Identity::Double(value),
+            Ranksep::Equally(value) => Identity::OwnedQuoted(format!("{} equally", Identity::Double(value))),
+            Ranksep::List(values) => Identity::OwnedQuoted(values.into_iter()
+                .map(|value| Identity::Double(value).to_string())
+                .collect::<Vec<_>>()
+                .join(":")),
+        }
+    }
+}
+
+impl<'a> TryFrom<Ranksep> for Identity<'a> {
+    type Error = Error;
+
+    fn try_from(ranksep: Ranksep) -> Result<Self, Error> {
+        let values = match &ranksep {
+            Ranksep::Value(value) | Ranksep::Equally(value) => std::slice::from_ref(value),
+            Ranksep::List(values) if values.is_empty() => return Err(Error::EmptyList),
+            Ranksep::List(values) => values.as_slice(),
+        };
+        match values.iter().find(|value| !value.is_finite() || **value < 0.0) {
+            Some(value) if !value.is_finite() => Err(Error::NonFiniteNumber(*value)),
+            Some(value) => Err(Error::NegativeNumber(*value)),
+            None => Ok(ranksep.render()),
+        }
+    }
+}
+
+/// The maximum size of the drawing in inches, see [size](https://graphviz.org/docs/attrs/size/).
+/// With `fill` the drawing is scaled up as well, until it reaches the size (written with a `!`).
+/// A plain `f64` converts into a square that is not filled.
+/// ```
+/// use tabbycat::attributes::*;
+/// use tabbycat::AttrList;
+/// let attrs = AttrList::new()
+///     .add_pair(size(7.5))
+///     .add_pair(size(Size::Rect { width: 8.0, height: 6.0, fill: true }));
+/// assert_eq!(r#"[size=7.5,size="8,6!"]"#, attrs.to_string());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Size {
+    Square {
+        size: f64,
+        fill: bool,
+    },
+    Rect {
+        width: f64,
+        height: f64,
+        fill: bool,
+    },
+}
+
+impl From<f64> for Size {
+    fn from(size: f64) -> Self {
+        Size::Square { size, fill: false }
+    }
+}
+
+impl<'a> From<Size> for Identity<'a> {
+    fn from(size: Size) -> Self {
+        match size {
+            Size::Square { size, fill: false } => Identity::Double(size),
+            Size::Square { size, fill: true } => Identity::OwnedQuoted(format!("{}!", Identity::Double(size))),
+            Size::Rect { width, height, fill } => Identity::OwnedQuoted(format!("{},{}{}",
+                Identity::Double(width), Identity::Double(height), if fill { "!" } else { "" })),
+        }
+    }
+}
+
+/// The [page](https://graphviz.org/docs/attrs/page/) size in inches for paged output. Unlike
+/// `Size` it has no `fill`, which graphviz does not support for pages. A plain `f64` converts
+/// into a square page.
+/// ```compile_fail
+/// use tabbycat::attributes::*;
+/// let filled = page(Size::Square { size: 5.0, fill: true });
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum PageSize {
+    Square(f64),
+    Rect {
+        width: f64,
+        height: f64,
+    },
+}
+
+impl From<f64> for PageSize {
+    fn from(size: f64) -> Self {
+        PageSize::Square(size)
+    }
+}
+
+impl<'a> From<PageSize> for Identity<'a> {
+    fn from(page: PageSize) -> Self {
+        match page {
+            PageSize::Square(size) => Identity::Double(size),
+            PageSize::Rect { width, height } => Identity::OwnedQuoted(format!("{},{}",
+                Identity::Double(width), Identity::Double(height))),
+        }
+    }
+}
+
+/// How the drawing is fitted to `size`, see [ratio](https://graphviz.org/docs/attrs/ratio/).
+/// A plain `f64` converts into `Ratio::Aspect`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ratio {
+    /// The desired height / width of the drawing
+    Aspect(f64),
+    Fill,
+    Compress,
+    Expand,
+    Auto,
+}
+
+impl From<f64> for Ratio {
+    fn from(aspect: f64) -> Self {
+        Ratio::Aspect(aspect)
+    }
+}
+
+impl<'a> From<Ratio> for Identity<'a> {
+    fn from(ratio: Ratio) -> Self {
+        match ratio {
+            Ratio::Aspect(aspect) => Identity::Double(aspect),
+            Ratio::Fill => Identity::String("fill"),
+            Ratio::Compress => Identity::String("compress"),
+            Ratio::Expand => Identity::String("expand"),
+            Ratio::Auto => Identity::String("auto"),
+        }
+    }
+}
+
 /// Point type in the dot language
 pub enum Point {
     Point2D {
@@ -326,8 +682,18 @@ impl<'a> From<Point> for Identity<'a> {
         }
     }
 }
-/// Paging Direction
-#[derive(Debug)]
+/// Paging Direction, the order pages are emitted in: first the major direction, then the minor one,
+/// e.g. `TL` goes from top to bottom, each row from left to right.
+/// ```
+/// use tabbycat::attributes::*;
+/// use tabbycat::AttrList;
+/// let attrs = AttrList::new()
+///     .add_pair(page(PageSize::Rect { width: 8.5, height: 11.0 }))
+///     .add_pair(pagedir(PageDir::TL))
+///     .add_pair(margin(0.5));
+/// assert_eq!(r#"[page="8.5,11",pagedir=TL,margin=0.5]"#, attrs.to_string());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PageDir {
     BL,
     BR,
@@ -409,8 +775,11 @@ impl<'a> From<DirType> for Identity<'a> {
     }
 }
 
+/// Shorthand for `DirType`, matching the attribute name `dir`
+pub type Dir = DirType;
+
 /// Styles, see the [document](https://www.graphviz.org/doc/info/attrs.html#k:style)
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum Style {
     None,
     Invisible,
@@ -423,11 +792,13 @@ pub enum Style {
     Filled,
     Striped,
     Wedged,
+    Radial,
+    Tapered,
 }
 
-impl<'a> From<Style> for Identity<'a> {
-    fn from(dir: Style) -> Self {
-        Identity::String(match dir {
+impl Style {
+    fn style_str(self) -> &'static str {
+        match self {
             Style::None => "none",
             Style::Invisible => "invisible",
             Style::Solid => "solid",
@@ -439,10 +810,108 @@ impl<'a> From<Style> for Identity<'a> {
             Style::Filled => "filled",
             Style::Striped => "striped",
             Style::Wedged => "wedged",
-        })
+            Style::Radial => "radial",
+            Style::Tapered => "tapered",
+        }
     }
 }
 
+impl<'a> From<Style> for Identity<'a> {
+    fn from(dir: Style) -> Self {
+        Identity::String(dir.style_str())
+    }
+}
+
+/// Combine several styles into one `style` attribute, e.g. `style="filled,rounded"`.
+pub fn styles<'a, I: IntoIterator<Item=Style>>(styles: I) -> AttrPair<'a> {
+    let value = styles.into_iter()
+        .map(Style::style_str)
+        .collect::<Vec<_>>()
+        .join(",");
+    (Identity::String("style"), Identity::OwnedQuoted(value))
+}
+
+/// Combine several CSS classes into one `class` attribute for SVG output, e.g. `class="node important"`.
+/// `id` and `class` are plain attributes, so they can be set on the graph, clusters, nodes and edges:
+/// ```
+/// use tabbycat::attributes::*;
+/// use tabbycat::AttrList;
+/// let attrs = AttrList::new()
+///     .add_pair(id("node-1"))
+///     .add_pair(classes(vec!["node", "important"]));
+/// assert_eq!(r#"[id="node-1",class="node important"]"#, attrs.to_string());
+/// ```
+pub fn classes<'a, 'b, I: IntoIterator<Item=&'b str>>(classes: I) -> AttrPair<'a> {
+    let value = classes.into_iter().collect::<Vec<_>>().join(" ");
+    (Identity::String("class"), Identity::OwnedQuoted(value))
+}
+
+/// An external label, placed next to a node or edge by `xlabel`, together with `forcelabels=true`
+/// so graphviz places it even where it overlaps other nodes or labels. Graphviz reads `forcelabels`
+/// from the root graph only (where it defaults to true), so set it there if it was turned off.
+/// ```
+/// use tabbycat::attributes::*;
+/// use tabbycat::AttrList;
+/// assert_eq!(r#"[xlabel="note",forcelabels=true]"#, AttrList::new().extend(external_label("note")).to_string());
+/// ```
+pub fn external_label(text: &str) -> Vec<AttrPair<'_>> {
+    vec![xlabel(text), forcelabels(true)]
+}
+
+/// Make a node, edge or cluster a link in SVG and image map output: `URL` is opened in a new
+/// window (`target="_blank"`) and `tooltip` is shown on hover.
+/// ```
+/// use tabbycat::attributes::*;
+/// use tabbycat::AttrList;
+/// let attrs = AttrList::new().extend(link("https://graphviz.org", "Graphviz"));
+/// assert_eq!(r#"[URL="https://graphviz.org",tooltip="Graphviz",target="_blank"]"#, attrs.to_string());
+/// ```
+pub fn link<'a>(url: &'a str, tooltip_text: &'a str) -> Vec<AttrPair<'a>> {
+    vec![URL(url), tooltip(tooltip_text), target("_blank")]
+}
+
+/// Declare the layers of the graph for layered output, joined with `:`, the default `layersep`.
+/// Nodes, edges and clusters are put on layers with `layer`, e.g. `layer("b")` or `layer("all")`.
+/// ```
+/// use tabbycat::attributes::*;
+/// use tabbycat::AttrList;
+/// let attrs = AttrList::new().add_pair(layers(&["a", "b", "c"]));
+/// assert_eq!(r#"[layers="a:b:c"]"#, attrs.to_string());
+/// ```
+pub fn layers<'a>(names: &[&str]) -> AttrPair<'a> {
+    (Identity::String("layers"), Identity::OwnedQuoted(names.join(":")))
+}
+
+/// Declare the layers of the graph like `layers`, joined with `separator` instead of `:`. The
+/// separator is set as `layersep` too, so graphviz splits the list the same way.
+pub fn layers_separated<'a>(names: &[&str], separator: char) -> Vec<AttrPair<'a>> {
+    vec![
+        (Identity::String("layersep"), Identity::OwnedQuoted(separator.to_string())),
+        (Identity::String("layers"), Identity::OwnedQuoted(names.join(&separator.to_string()))),
+    ]
+}
+
+/// Alignment of the lines of a label, see `label_lines`
+#[derive(Debug, Clone, Copy)]
+pub enum Align {
+    Center,
+    Left,
+    Right,
+}
+
+/// A multi-line `label`. Left and right aligned lines each end with `\l` or `\r`, centered lines
+/// are separated by `\n`, e.g. `label_lines(&["a", "b"], Align::Left)` gives `label="a\lb\l"`.
+/// Quotes and backslashes inside the lines are escaped, so the lines are shown as they are.
+pub fn label_lines<'a>(lines: &[&str], align: Align) -> AttrPair<'a> {
+    let escaped = lines.iter().map(|line| line.replace('\\', "\\\\").replace('"', "\\\""));
+    let value = match align {
+        Align::Center => escaped.collect::<Vec<_>>().join("\\n"),
+        Align::Left => escaped.map(|line| line + "\\l").collect(),
+        Align::Right => escaped.map(|line| line + "\\r").collect(),
+    };
+    (Identity::String("label"), Identity::EscString(value))
+}
+
 
 /// Shapes of the node
 #[derive(Debug)]
@@ -572,6 +1041,7 @@ pub enum ArrowShape {
 /// Notice that we are actually listing a union of `X11` colors and `SVG` colors, you should be aware of
 /// what color scheme you are really using.
 /// For unlisted colors, see the instructions above on how to implement your own attribute pairs.
+#[derive(Debug)]
 pub enum Color {
     Rgb(u8, u8, u8),
     Rgba(u8, u8, u8, u8),
@@ -1373,18 +1843,46 @@ fn arrow_str(ashape: ArrowShape) -> &'static str {
     }
 }
 
+impl From<(u8, u8, u8)> for Color {
+    fn from((r, g, b): (u8, u8, u8)) -> Self {
+        Color::Rgb(r, g, b)
+    }
+}
+
+impl From<(u8, u8, u8, u8)> for Color {
+    fn from((r, g, b, a): (u8, u8, u8, u8)) -> Self {
+        Color::Rgba(r, g, b, a)
+    }
+}
+
 impl<'a> From<Color> for Identity<'a> {
     fn from(xc: Color) -> Self {
-        if let Color::Rgb(r, g, b) = xc {
-            return Identity::RGBA(r, g, b, 255);
+        match xc {
+            Color::Rgb(r, g, b) => Identity::RGBA(r, g, b, 255),
+            Color::Rgba(r, g, b, a) => Identity::RGBA(r, g, b, a),
+            Color::HSV(h, s, v) => Identity::HSV(h, s, v),
+            named => Identity::String(named.name().unwrap_or_default()),
         }
-        if let Color::Rgba(r, g, b, a) = xc {
-            return Identity::RGBA(r, g, b, a);
-        }
-        if let Color::HSV(h, s, v) = xc {
-            return Identity::HSV(h, s, v);
+    }
+}
+
+/// The color as written inside a `colorList`: the name of a named color, `#rrggbb` or `#rrggbbaa`,
+/// or `h,+s,+v`.
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Color::Rgb(r, g, b) | Color::Rgba(r, g, b, 255) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+            Color::Rgba(r, g, b, a) => write!(f, "#{:02x}{:02x}{:02x}{:02x}", r, g, b, a),
+            Color::HSV(h, s, v) => write!(f, "{},+{},+{}", h, s, v),
+            _ => f.write_str(self.name().unwrap_or_default()),
         }
-        Identity::String(match xc {
+    }
+}
+
+impl Color {
+    /// The name of a named color, `None` for `Rgb`, `Rgba` and `HSV`.
+    fn name(&self) -> Option<&'static str> {
+        Some(match self {
             Color::Aliceblue => "aliceblue",
             Color::Antiquewhite => "antiquewhite",
             Color::Antiquewhite1 => "antiquewhite1",
@@ -2053,8 +2551,69 @@ impl<'a> From<Color> for Identity<'a> {
             Color::Yellow3 => "yellow3",
             Color::Yellow4 => "yellow4",
             Color::Yellowgreen => "yellowgreen",
-            _ => unsafe { unreachable_unchecked() }
+            Color::Rgb(..) | Color::Rgba(..) | Color::HSV(..) => return None,
         })
     }
 }
 
+/// A [colorList](https://graphviz.org/docs/attr-types/colorList/) of colors with optional weights,
+/// rendered as `red;0.3:blue`. It is accepted by `color`, `fillcolor` and `bgcolor`, where it draws
+/// gradients or multi-colored edges. A single `Color` converts into a one-entry list.
+///
+/// A weight is the fraction of the area a color covers. Weights outside `0..=1`, or ones that add
+/// up to more than 1, are rejected:
+/// ```
+/// use tabbycat::attributes::*;
+/// use tabbycat::{AttrList, Error};
+/// let gradient = ColorList::new(Color::Red, Some(0.3)).and_then(|list| list.add(Color::Blue, None)).unwrap();
+/// assert_eq!(r#"[fillcolor="red;0.3:blue"]"#, AttrList::new().add_pair(fillcolor(gradient)).to_string());
+/// assert_eq!(Error::InvalidWeight(1.5), ColorList::new(Color::Red, Some(0.75)).unwrap().add(Color::Blue, Some(0.75)).unwrap_err());
+/// ```
+#[derive(Debug)]
+pub struct ColorList(Vec<(Color, Option<f32>)>);
+
+impl ColorList {
+    /// Create a list starting with `color`, see `add`
+    pub fn new(color: Color, weight: Option<f32>) -> Result<Self, Error> {
+        ColorList(Vec::new()).add(color, weight)
+    }
+    /// Append a color, `weight` is the fraction of the area (between 0 and 1) it covers. The
+    /// weights of the list must not add up to more than 1.
+    pub fn add(mut self, color: Color, weight: Option<f32>) -> Result<Self, Error> {
+        if let Some(weight) = weight {
+            let total = self.0.iter().filter_map(|(_, weight)| *weight).sum::<f32>() + weight;
+            if !weight.is_finite() {
+                return Err(Error::NonFiniteNumber(weight.into()));
+            } else if !(0.0..=1.0).contains(&weight) {
+                return Err(Error::InvalidWeight(weight.into()));
+            } else if total > 1.0 + f32::EPSILON {
+                return Err(Error::InvalidWeight(total.into()));
+            }
+        }
+        self.0.push((color, weight));
+        Ok(self)
+    }
+}
+
+impl From<Color> for ColorList {
+    fn from(color: Color) -> Self {
+        ColorList(vec![(color, None)])
+    }
+}
+
+impl<'a> From<ColorList> for Identity<'a> {
+    fn from(list: ColorList) -> Self {
+        let mut entries = list.0;
+        if let [(_, None)] = entries.as_slice() {
+            return Identity::from(entries.pop().unwrap().0);
+        }
+        let value = entries.into_iter()
+            .map(|(color, weight)| match weight {
+                Some(w) => format!("{};{}", color, w),
+                None => color.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(":");
+        Identity::OwnedQuoted(value)
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{EdgeNode, Graph, Identity, Port, Stmt, SubGraph};
+
+/// The top level statements that differ between two graphs, see `Graph::diff`.
+/// A changed subgraph is reported as a whole, statements are listed in the order of their graph.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphDiff<'g, 'a> {
+    /// Statements only found in the other graph
+    pub added: Vec<&'g Stmt<'a>>,
+    /// Statements only found in this graph
+    pub removed: Vec<&'g Stmt<'a>>,
+    /// Statements found in both graphs with different content, as `(this, other)`
+    pub changed: Vec<(&'g Stmt<'a>, &'g Stmt<'a>)>,
+}
+
+impl<'g, 'a> GraphDiff<'g, 'a> {
+    /// Whether both graphs have the same statements
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// What makes two statements "the same" statement in two versions of a graph. Ids are keyed by
+/// their unquoted text, so `a` and `"a"` match whichever `Identity` variant holds them.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum StmtKey {
+    Node(String),
+    /// The edge rendered without its attributes, so ports and endpoints are part of the key.
+    Edge(String),
+    SubGraph(String),
+    Equation(String),
+    /// Statements that have no name, like comments and anonymous subgraphs, only match when equal.
+    Other(String),
+}
+
+/// The id as a quoted string of its text, which renders the same for every variant.
+fn text_id(id: &Identity<'_>) -> Identity<'static> {
+    Identity::OwnedQuoted(id.unquoted())
+}
+
+fn key(stmt: &Stmt<'_>) -> StmtKey {
+    match stmt {
+        Stmt::Node { id, .. } => StmtKey::Node(id.unquoted()),
+        Stmt::Edge(edge) => {
+            let mut edge = edge.clone();
+            edge.attr = None;
+            let nodes = std::iter::once(&mut edge.node).chain(edge.body.iter_mut().map(|body| &mut body.node));
+            for node in nodes {
+                if let EdgeNode::Node { id, port } = node {
+                    *id = text_id(id);
+                    if let Some(Port::ID(port_id, _)) = port {
+                        *port_id = text_id(port_id);
+                    }
+                }
+            }
+            StmtKey::Edge(edge.to_string())
+        }
+        Stmt::SubGraph(SubGraph::SubGraph { id: Some(id), .. }) => StmtKey::SubGraph(id.unquoted()),
+        Stmt::Equation(k, _) => StmtKey::Equation(k.unquoted()),
+        other => StmtKey::Other(other.to_string()),
+    }
+}
+
+impl<'a> Graph<'a> {
+    /// Compare the top level statements of this graph with those of `other`. Nodes are matched by
+    /// id, edges by their endpoints and ports, named subgraphs by id and equations by key; a matched
+    /// statement with other attributes or content is reported as changed. Statements that repeat a
+    /// key are matched in order. The graph type, id and header are not compared.
+    /// ```
+    /// use tabbycat::{EdgeOp, GraphBuilder, GraphType, Identity, StmtList};
+    /// let graph = |stmts| GraphBuilder::default()
+    ///     .graph_type(GraphType::DiGraph)
+    ///     .strict(false)
+    ///     .stmts(stmts)
+    ///     .build()
+    ///     .unwrap();
+    /// let a = graph(StmtList::new().add_node(Identity::id("a").unwrap(), None, None));
+    /// let b = graph(StmtList::new().add_node(Identity::id("b").unwrap(), None, None));
+    /// let diff = a.diff(&b);
+    /// assert_eq!(vec!["a"], diff.removed.iter().map(|stmt| stmt.to_string()).collect::<Vec<_>>());
+    /// assert_eq!(vec!["b"], diff.added.iter().map(|stmt| stmt.to_string()).collect::<Vec<_>>());
+    /// assert!(a.diff(&a).is_empty());
+    /// ```
+    pub fn diff<'g>(&'g self, other: &'g Graph<'a>) -> GraphDiff<'g, 'a> {
+        let mut unmatched: HashMap<StmtKey, VecDeque<usize>> = HashMap::new();
+        for (index, stmt) in other.stmts.0.iter().enumerate() {
+            unmatched.entry(key(stmt)).or_default().push_back(index);
+        }
+        let mut matched = vec![false; other.stmts.0.len()];
+        let mut diff = GraphDiff { added: Vec::new(), removed: Vec::new(), changed: Vec::new() };
+        for stmt in self.stmts.0.iter() {
+            match unmatched.get_mut(&key(stmt)).and_then(VecDeque::pop_front) {
+                Some(index) => {
+                    matched[index] = true;
+                    if stmt != &other.stmts.0[index] {
+                        diff.changed.push((stmt, &other.stmts.0[index]));
+                    }
+                }
+                None => diff.removed.push(stmt),
+            }
+        }
+        diff.added = other.stmts.0.iter()
+            .zip(matched)
+            .filter(|(_, matched)| !matched)
+            .map(|(stmt, _)| stmt)
+            .collect();
+        diff
+    }
+}
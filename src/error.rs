@@ -0,0 +1,45 @@
+use std::fmt::{Formatter, Result};
+
+use crate::GraphType;
+
+/// The errors of this crate.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The string is not a valid dot id, see `Identity::id` for the lexical rule.
+    InvalidIdentity(String),
+    /// `NaN` and infinities are not dot numerals, see `Identity::finite`.
+    NonFiniteNumber(f64),
+    /// A width or size that must not be negative, e.g. the pen width of `attributes::stroke`.
+    NegativeNumber(f64),
+    /// A list attribute without any value, e.g. an empty `attributes::Ranksep::List`.
+    EmptyList,
+    /// A color weight outside `0..=1`, or the total of the weights of an `attributes::ColorList`
+    /// when it exceeds 1.
+    InvalidWeight(f64),
+    /// `Graph::merge` was given a graph of another type, or one that differs in being strict.
+    IncompatibleGraphs {
+        graph_type: GraphType,
+        strict: bool,
+        other_graph_type: GraphType,
+        other_strict: bool,
+    },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Error::InvalidIdentity(data) => write!(f, "invalid identity format: {:?}", data),
+            Error::NonFiniteNumber(number) => write!(f, "non-finite number {}", number),
+            Error::NegativeNumber(number) => write!(f, "negative number {}", number),
+            Error::EmptyList => write!(f, "empty list"),
+            Error::InvalidWeight(weight) => write!(f, "color weight {} outside of 0..=1", weight),
+            Error::IncompatibleGraphs { graph_type, strict, other_graph_type, other_strict } =>
+                write!(f, "cannot merge a {}{:?} into a {}{:?}",
+                       if *other_strict { "strict " } else { "" }, other_graph_type,
+                       if *strict { "strict " } else { "" }, graph_type),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
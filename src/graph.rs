@@ -1,21 +1,32 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
 use std::fmt::{Formatter, Result};
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::sync::OnceLock;
 
 use derive_builder::Builder;
 
+use crate::Error;
+
 /// The list of attributes
-#[derive(Clone, Debug)]
-pub struct AttrList<'a> (pub(crate) Vec<Vec<(Identity<'a>, Identity<'a>)>>);
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AttrList<'a> (#[cfg_attr(feature = "serde", serde(borrow))] pub(crate) Vec<Vec<(Identity<'a>, Identity<'a>)>>);
 
 /// The list of statements, including:
 /// - node declaration
 /// - edge declaration
 /// - subgraph declaration
 /// - global attributes
-#[derive(Clone, Debug)]
-pub struct StmtList<'a>(pub(crate) Vec<Stmt<'a>>);
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StmtList<'a>(#[cfg_attr(feature = "serde", serde(borrow))] pub(crate) Vec<Stmt<'a>>);
 
 /// The types of graphs
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GraphType {
     /// undirected graph
     Graph,
@@ -24,7 +35,8 @@ pub enum GraphType {
 }
 
 /// The types of global attributes
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AttrType {
     /// attributes for graph
     Graph,
@@ -39,9 +51,11 @@ pub enum AttrType {
 /// - `Identity::id` for checked id strings
 /// - `Identity::quoted` for quoted safe strings
 /// - `Identity::from` for numeral types
+/// - `Identity::Owned` and `Identity::OwnedQuoted` for strings computed at runtime
 ///
 /// However, if you need to create some special identities like `HTML`, you can use `Identity::String` directly.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Identity<'a> {
     String(&'a str),
     Usize(usize),
@@ -57,11 +71,23 @@ pub enum Identity<'a> {
     U64(u64),
     I128(i128),
     U128(u128),
+    /// Floating point numbers are written as the shortest decimal that reads back to the same value,
+    /// without exponent (dot numerals do not allow one), so the output does not depend on the platform.
     Float(f32),
+    /// Written like `Float`.
     Double(f64),
+    /// A quoted string, see `Identity::quoted` for how its text is escaped.
     Quoted(&'a str),
+    /// An unquoted string owning its content, rendered like `String`.
+    Owned(String),
+    /// A quoted string owning its content, e.g. one computed at runtime.
+    OwnedQuoted(String),
+    /// An [escString](https://graphviz.org/docs/attr-types/escString/) whose content is already
+    /// escaped for graphviz. It is written between quotes as it is, so backslash sequences like
+    /// `\|` or `\l` reach graphviz unchanged.
+    EscString(String),
     #[cfg(feature = "attributes")]
-    ArrowName([Option<&'a str>; 4]),
+    ArrowName(#[cfg_attr(feature = "serde", serde(borrow))] [Option<&'a str>; 4]),
     #[cfg(feature = "attributes")]
     RGBA(u8, u8, u8, u8),
     #[cfg(feature = "attributes")]
@@ -73,72 +99,123 @@ pub enum Identity<'a> {
 }
 
 /// Graph in the dot language. You can construct it with the `GraphBuilder`.
-#[derive(Builder, Clone, Debug)]
-#[builder(pattern = "owned")]
+/// Graphs and all their parts compare structurally, except for identities, which compare by the
+/// text graphviz reads, see `Identity`.
+///
+/// `{}` writes the whole graph on one line. The alternate format `{:#}` pretty prints it, see
+/// `Graph::to_string_pretty`; the width is the indent per level, so `{:#2}` indents by two spaces
+/// and `{:#}` by four.
+#[derive(Builder, Clone, Debug, PartialEq, Eq)]
+#[builder(name = "GraphFieldsBuilder", private, pattern = "owned", build_fn(name = "build_fields"))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Graph<'a> {
-    graph_type: GraphType,
-    strict: bool,
-    #[builder(setter(strip_option))]
-    id: Option<Identity<'a>>,
-    stmts: StmtList<'a>,
+    pub(crate) graph_type: GraphType,
+    pub(crate) strict: bool,
+    #[builder(setter(strip_option), default)]
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub(crate) id: Option<Identity<'a>>,
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub(crate) stmts: StmtList<'a>,
+    /// A comment written before the graph, e.g. to say which tool generated it. Pretty output
+    /// writes it as `//` comments, one per line, compact output as a single `/* ... */` comment.
+    #[builder(setter(into, strip_option), default)]
+    #[cfg_attr(feature = "serde", serde(borrow, default))]
+    pub(crate) header: Option<Cow<'a, str>>,
 }
 
 /// A single line of statement. You should not construct it directly in most cases.
 /// We still expose this type because we only implement a subset of dot language so
 /// you may need to write special statements on your own.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Stmt<'a> {
-    Edge(Edge<'a>),
+    Edge(#[cfg_attr(feature = "serde", serde(borrow))] Edge<'a>),
     Node {
+        #[cfg_attr(feature = "serde", serde(borrow))]
         id: Identity<'a>,
+        #[cfg_attr(feature = "serde", serde(borrow))]
         port: Option<Port<'a>>,
+        #[cfg_attr(feature = "serde", serde(borrow))]
         attr: Option<AttrList<'a>>,
     },
-    Attr(AttrType, AttrList<'a>),
-    Equation(Identity<'a>, Identity<'a>),
-    SubGraph(SubGraph<'a>),
+    Attr(AttrType, #[cfg_attr(feature = "serde", serde(borrow))] AttrList<'a>),
+    Equation(#[cfg_attr(feature = "serde", serde(borrow))] Identity<'a>, #[cfg_attr(feature = "serde", serde(borrow))] Identity<'a>),
+    /// A `/* ... */` comment, any `*/` inside the text is broken up so it cannot end the comment early.
+    Comment(#[cfg_attr(feature = "serde", serde(borrow))] Cow<'a, str>),
+    /// A `//` comment, one per line of the text. Compact output has no line breaks to end it,
+    /// so there it is written as a `/* ... */` comment like `Stmt::Comment`.
+    LineComment(#[cfg_attr(feature = "serde", serde(borrow))] Cow<'a, str>),
+    SubGraph(#[cfg_attr(feature = "serde", serde(borrow))] SubGraph<'a>),
 }
 
-/// An edge in the dot language.
-#[derive(Clone, Debug)]
+/// An edge in the dot language. You can construct it by chaining from `Edge::head_node`
+/// or `Edge::head_subgraph`, or with the `EdgeBuilder`, which requires a head:
+/// ```
+/// use tabbycat::{Edge, EdgeBuilder, EdgeNode, Identity};
+/// let edge = EdgeBuilder::default()
+///     .head(EdgeNode::Node { id: Identity::id("a").unwrap(), port: None })
+///     .arrow_to_node(Identity::id("b").unwrap(), None)
+///     .build()
+///     .unwrap();
+/// assert_eq!("a->b", edge.to_string());
+/// assert!(EdgeBuilder::default().build().is_err());
+/// ```
+#[derive(Builder, Clone, Debug, PartialEq, Eq)]
+#[builder(pattern = "owned")]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Edge<'a> {
+    #[builder(setter(name = "head"))]
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub(crate) node: EdgeNode<'a>,
+    #[builder(setter(custom), default)]
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub(crate) body: Vec<EdgeBody<'a>>,
+    #[builder(setter(strip_option), default)]
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub(crate) attr: Option<AttrList<'a>>,
 }
 
 /// The tag of the edge operation
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EdgeOp {
     Arrow,
     Line,
 }
 
 /// A body part of edge
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EdgeBody<'a> {
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub(crate) node: EdgeNode<'a>,
     pub(crate) op: EdgeOp,
 }
 
-/// A node of the edge
-#[derive(Clone, Debug)]
+/// A node of the edge. Only node endpoints carry a port, the dot language has no ports on subgraphs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EdgeNode<'a> {
     Node {
+        #[cfg_attr(feature = "serde", serde(borrow))]
         id: Identity<'a>,
+        #[cfg_attr(feature = "serde", serde(borrow))]
         port: Option<Port<'a>>,
     },
-    SubGraph(SubGraph<'a>),
+    SubGraph(#[cfg_attr(feature = "serde", serde(borrow))] SubGraph<'a>),
 }
 
 /// A subgraph in the dot language
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SubGraph<'a> {
     SubGraph {
+        #[cfg_attr(feature = "serde", serde(borrow))]
         id: Option<Identity<'a>>,
+        #[cfg_attr(feature = "serde", serde(borrow))]
         stmts: Box<StmtList<'a>>,
     },
-    Cluster(Box<StmtList<'a>>),
+    Cluster(#[cfg_attr(feature = "serde", serde(borrow))] Box<StmtList<'a>>),
 }
 
 impl<'a> SubGraph<'a> {
@@ -155,20 +232,237 @@ impl<'a> SubGraph<'a> {
     ///     A -> B;
     /// }
     /// ```
+    #[allow(clippy::self_named_constructors)]
     pub fn subgraph(id: Option<Identity<'a>>, list: StmtList<'a>) -> Self {
         SubGraph::SubGraph { id, stmts: Box::new(list) }
     }
+    /// The statements inside the subgraph
+    pub(crate) fn stmts(&self) -> &StmtList<'a> {
+        match self {
+            SubGraph::SubGraph { stmts, .. } | SubGraph::Cluster(stmts) => stmts,
+        }
+    }
+    pub(crate) fn stmts_mut(&mut self) -> &mut StmtList<'a> {
+        match self {
+            SubGraph::SubGraph { stmts, .. } | SubGraph::Cluster(stmts) => stmts,
+        }
+    }
 }
 
-/// The port suffix.
+/// A fluent builder for `SubGraph`, mirroring the `StmtList` methods so that a subgraph
+/// can be written inline in its parent's chain:
+/// ```
+/// use tabbycat::{Edge, Identity, SubGraphBuilder};
+/// let sub = SubGraphBuilder::named(Identity::id("cluster_0").unwrap())
+///     .label("group")
+///     .add_node(Identity::id("a").unwrap(), None, None)
+///     .add_edge(Edge::head_node(Identity::id("a").unwrap(), None)
+///         .arrow_to_node(Identity::id("b").unwrap(), None))
+///     .build();
+/// assert_eq!("subgraph cluster_0 {label=\"group\";a;a->b;}", sub.to_string());
+/// ```
 #[derive(Clone, Debug)]
+pub struct SubGraphBuilder<'a> {
+    id: Option<Identity<'a>>,
+    stmts: StmtList<'a>,
+}
+
+impl<'a> SubGraphBuilder<'a> {
+    /// Start a cluster, which builds into `SubGraph::Cluster` (`{...}`)
+    pub fn cluster() -> Self {
+        SubGraphBuilder { id: None, stmts: StmtList::new() }
+    }
+    /// Start a subgraph with the given id, which builds into `subgraph <id> {...}`
+    pub fn named(id: Identity<'a>) -> Self {
+        SubGraphBuilder { id: Some(id), stmts: StmtList::new() }
+    }
+    /// Add a statement
+    #[allow(clippy::should_implement_trait)]
+    #[must_use]
+    pub fn add(mut self, stmt: Stmt<'a>) -> Self {
+        self.stmts = self.stmts.add(stmt);
+        self
+    }
+    /// Add a node statement
+    #[must_use]
+    pub fn add_node(mut self, id: Identity<'a>, port: Option<Port<'a>>, attr: Option<AttrList<'a>>) -> Self {
+        self.stmts = self.stmts.add_node(id, port, attr);
+        self
+    }
+    /// Add an edge statement
+    #[must_use]
+    pub fn add_edge(mut self, edge: Edge<'a>) -> Self {
+        self.stmts = self.stmts.add_edge(edge);
+        self
+    }
+    /// Add a nested subgraph
+    #[must_use]
+    pub fn add_subgraph(mut self, sub: SubGraph<'a>) -> Self {
+        self.stmts = self.stmts.add_subgraph(sub);
+        self
+    }
+    /// Add a global attribute
+    #[must_use]
+    pub fn add_attr(mut self, attr_type: AttrType, attr_list: AttrList<'a>) -> Self {
+        self.stmts = self.stmts.add_attr(attr_type, attr_list);
+        self
+    }
+    /// Add an equation
+    #[must_use]
+    pub fn add_equation(mut self, a: Identity<'a>, b: Identity<'a>) -> Self {
+        self.stmts = self.stmts.add_equation(a, b);
+        self
+    }
+    /// Set the label of the subgraph, written as a `label="..."` equation
+    #[must_use]
+    pub fn label(self, label: &'a str) -> Self {
+        self.add_equation(Identity::String("label"), Identity::quoted(label))
+    }
+    /// Finish the subgraph
+    #[must_use]
+    pub fn build(self) -> SubGraph<'a> {
+        match self.id {
+            Some(id) => SubGraph::subgraph(Some(id), self.stmts),
+            None => SubGraph::cluster(self.stmts),
+        }
+    }
+}
+
+/// Builder for `Graph`. `graph_type`, `strict` and `stmts` must be set, `id` and `header` are
+/// optional. The attribute shortcuts are kept apart from the statements until `build`.
+#[derive(Default)]
+pub struct GraphBuilder<'a> {
+    fields: GraphFieldsBuilder<'a>,
+    graph_attrs: Option<AttrList<'a>>,
+}
+
+impl<'a> GraphBuilder<'a> {
+    /// Set whether the graph is directed
+    #[must_use]
+    pub fn graph_type(mut self, graph_type: GraphType) -> Self {
+        self.fields = self.fields.graph_type(graph_type);
+        self
+    }
+    /// Set whether the graph is `strict`, which merges repeated edges
+    #[must_use]
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.fields = self.fields.strict(strict);
+        self
+    }
+    /// Set the graph id
+    #[must_use]
+    pub fn id(mut self, id: Identity<'a>) -> Self {
+        self.fields = self.fields.id(id);
+        self
+    }
+    /// Set the statements of the graph, replacing any set before
+    #[must_use]
+    pub fn stmts(mut self, stmts: StmtList<'a>) -> Self {
+        self.fields = self.fields.stmts(stmts);
+        self
+    }
+    /// Set the comment written before the graph, e.g. to say which tool generated it
+    #[must_use]
+    pub fn header<T: Into<Cow<'a, str>>>(mut self, header: T) -> Self {
+        self.fields = self.fields.header(header);
+        self
+    }
+    /// Finish the graph. The pairs of the attribute shortcuts become a single `graph [...]`
+    /// statement in front of the statements, which may then be left unset.
+    pub fn build(self) -> std::result::Result<Graph<'a>, String> {
+        let mut fields = self.fields;
+        if self.graph_attrs.is_some() && fields.stmts.is_none() {
+            fields = fields.stmts(StmtList::new());
+        }
+        let mut graph = fields.build_fields()?;
+        if let Some(attrs) = self.graph_attrs {
+            graph.stmts.0.insert(0, Stmt::Attr(AttrType::Graph, attrs));
+        }
+        Ok(graph)
+    }
+}
+
+/// Shortcuts for common graph attributes. They are kept apart from the statements and written
+/// as one `graph [...]` statement in front of them, so they work before or after `stmts`:
+/// ```
+/// use tabbycat::attributes::{Color, RankDir};
+/// use tabbycat::{GraphBuilder, GraphType, Identity, StmtList};
+/// let graph = GraphBuilder::default()
+///     .graph_type(GraphType::DiGraph)
+///     .strict(false)
+///     .bgcolor(Color::White)
+///     .stmts(StmtList::new().add_node(Identity::id("a").unwrap(), None, None))
+///     .rankdir(RankDir::LR)
+///     .build()
+///     .unwrap();
+/// assert_eq!("digraph {graph [bgcolor=white,rankdir=LR];a;}", graph.to_string());
+/// ```
+#[cfg(feature = "attributes")]
+impl<'a> GraphBuilder<'a> {
+    fn graph_attr(mut self, pair: crate::AttrPair<'a>) -> Self {
+        let attrs = self.graph_attrs.take().unwrap_or_default();
+        self.graph_attrs = Some(attrs.add_pair(pair));
+        self
+    }
+    /// Set the background color, written as `graph [bgcolor=...]`
+    #[must_use]
+    pub fn bgcolor<T: Into<crate::attributes::ColorList>>(self, color: T) -> Self {
+        self.graph_attr(crate::attributes::bgcolor(color))
+    }
+    /// Set the graph label, written as `graph [label="..."]`
+    #[must_use]
+    pub fn label(self, label: &'a str) -> Self {
+        self.graph_attr(crate::attributes::label(label))
+    }
+    /// Set the direction of the layout, written as `graph [rankdir=...]`
+    #[must_use]
+    pub fn rankdir(self, rankdir: crate::attributes::RankDir) -> Self {
+        self.graph_attr(crate::attributes::rankdir(rankdir))
+    }
+    /// Set how edges are drawn, written as `graph [splines=...]`
+    #[must_use]
+    pub fn splines(self, splines: crate::attributes::Splines) -> Self {
+        self.graph_attr(crate::attributes::splines(splines))
+    }
+}
+
+#[cfg(feature = "attributes")]
+impl<'a> SubGraph<'a> {
+    /// A subgraph filled with `fill` and titled `label`, which starts with a
+    /// `graph [style=filled,fillcolor=...,label="..."]` statement followed by `stmts`.
+    /// Graphviz only draws subgraphs as clusters when their id starts with `cluster`:
+    /// ```
+    /// use tabbycat::attributes::Color;
+    /// use tabbycat::{Identity, StmtList, SubGraph};
+    /// let sub = SubGraph::styled_cluster(Identity::id("cluster_db").unwrap(), Color::Lightgrey, "database",
+    ///                                    StmtList::new().add_node(Identity::id("a").unwrap(), None, None));
+    /// assert_eq!(r#"subgraph cluster_db {graph [style=filled,fillcolor=lightgrey,label="database"];a;}"#, sub.to_string());
+    /// ```
+    pub fn styled_cluster(id: Identity<'a>, fill: crate::attributes::Color, label: &'a str, stmts: StmtList<'a>) -> Self {
+        use crate::attributes::{fillcolor, style, Style};
+        let attrs = AttrList::new()
+            .add_pair(style(Style::Filled))
+            .add_pair(fillcolor(fill))
+            .add_pair(crate::attributes::label(label));
+        let mut list = StmtList::new().add_attr(AttrType::Graph, attrs);
+        list.0.extend(stmts.0);
+        SubGraph::subgraph(Some(id), list)
+    }
+}
+
+/// The port suffix. The port id names a field of a record label (see `RecordLabel::port_field`)
+/// or a cell of an HTML label, and the compass point selects a side of the node or of that field,
+/// so `Port::id_compass(Identity::String("f0"), Compass::North)` renders as `:f0:n`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Port<'a> {
-    ID(Identity<'a>, Option<Compass>),
+    ID(#[cfg_attr(feature = "serde", serde(borrow))] Identity<'a>, Option<Compass>),
     Compass(Compass),
 }
 
 /// Directions
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Compass {
     North,
     NorthEast,
@@ -190,6 +484,31 @@ impl<'a> IntoIterator for StmtList<'a> {
     }
 }
 
+impl<'a> FromIterator<Stmt<'a>> for StmtList<'a> {
+    fn from_iter<I: IntoIterator<Item=Stmt<'a>>>(iter: I) -> Self {
+        StmtList(iter.into_iter().collect())
+    }
+}
+
+impl<'a> Extend<Stmt<'a>> for StmtList<'a> {
+    fn extend<I: IntoIterator<Item=Stmt<'a>>>(&mut self, iter: I) {
+        self.0.extend(iter)
+    }
+}
+
+/// Collecting pairs produces a single bracket `[a=b,c=d]`, or an empty list if there are no pairs.
+/// Use `extend_list` to build several brackets.
+impl<'a> FromIterator<AttrPair<'a>> for AttrList<'a> {
+    fn from_iter<I: IntoIterator<Item=AttrPair<'a>>>(iter: I) -> Self {
+        let pairs: Vec<_> = iter.into_iter().collect();
+        if pairs.is_empty() {
+            AttrList::new()
+        } else {
+            AttrList(vec![pairs])
+        }
+    }
+}
+
 impl<'a> IntoIterator for AttrList<'a> {
     type Item = Vec<(Identity<'a>, Identity<'a>)>;
     type IntoIter = std::vec::IntoIter<Vec<(Identity<'a>, Identity<'a>)>>;
@@ -199,6 +518,33 @@ impl<'a> IntoIterator for AttrList<'a> {
     }
 }
 
+impl<'b, 'a> IntoIterator for &'b StmtList<'a> {
+    type Item = &'b Stmt<'a>;
+    type IntoIter = std::slice::Iter<'b, Stmt<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<'b, 'a> IntoIterator for &'b mut StmtList<'a> {
+    type Item = &'b mut Stmt<'a>;
+    type IntoIter = std::slice::IterMut<'b, Stmt<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter_mut()
+    }
+}
+
+impl<'b, 'a> IntoIterator for &'b AttrList<'a> {
+    type Item = &'b Vec<(Identity<'a>, Identity<'a>)>;
+    type IntoIter = std::slice::Iter<'b, Vec<(Identity<'a>, Identity<'a>)>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
 impl<'a> From<bool> for Identity<'a> {
     fn from(flag: bool) -> Self {
         Identity::Bool(flag)
@@ -229,103 +575,1066 @@ impl<'a> From<u8> for Identity<'a> {
     }
 }
 
-impl<'a> From<u16> for Identity<'a> {
-    fn from(number: u16) -> Self {
-        Identity::U16(number)
+impl<'a> From<u16> for Identity<'a> {
+    fn from(number: u16) -> Self {
+        Identity::U16(number)
+    }
+}
+
+impl<'a> From<i16> for Identity<'a> {
+    fn from(number: i16) -> Self {
+        Identity::I16(number)
+    }
+}
+
+impl<'a> From<u32> for Identity<'a> {
+    fn from(number: u32) -> Self {
+        Identity::U32(number)
+    }
+}
+
+impl<'a> From<i32> for Identity<'a> {
+    fn from(number: i32) -> Self {
+        Identity::I32(number)
+    }
+}
+
+impl<'a> From<u64> for Identity<'a> {
+    fn from(number: u64) -> Self {
+        Identity::U64(number)
+    }
+}
+
+impl<'a> From<i64> for Identity<'a> {
+    fn from(number: i64) -> Self {
+        Identity::I64(number)
+    }
+}
+
+impl<'a> From<i128> for Identity<'a> {
+    fn from(number: i128) -> Self {
+        Identity::I128(number)
+    }
+}
+
+
+impl<'a> From<u128> for Identity<'a> {
+    fn from(number: u128) -> Self {
+        Identity::U128(number)
+    }
+}
+
+impl<'a> From<f32> for Identity<'a> {
+    fn from(number: f32) -> Self {
+        Identity::Float(number)
+    }
+}
+
+impl<'a> From<f64> for Identity<'a> {
+    fn from(number: f64) -> Self {
+        Identity::Double(number)
+    }
+}
+
+/// Identities compare by the text they are written as, without surrounding quotes: `Identity::String("a")`,
+/// `Identity::Quoted("a")` and `Identity::Owned("a".to_string())` are the same node, and numbers are
+/// equal to the numeral they render as, so `Identity::from(1)`, `Identity::from(1.0)` and
+/// `Identity::String("1")` are equal too. Quoted strings are compared after escaping, so they equal
+/// an `EscString` only if that renders the same. Hashing follows the same rule.
+/// ```
+/// use tabbycat::Identity;
+/// assert_eq!(Identity::id("a").unwrap(), Identity::quoted("a"));
+/// assert_eq!(Identity::from(1), Identity::from(1.0));
+/// assert_ne!(Identity::from(1.5), Identity::from(1));
+/// assert_ne!(Identity::quoted(r"a\|b"), Identity::EscString(r"a\|b".to_string()));
+/// assert_eq!(Identity::quoted(r"a\|b"), Identity::EscString(r"a\\|b".to_string()));
+/// ```
+impl<'a> PartialEq for Identity<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id_text() == other.id_text()
+    }
+}
+
+impl<'a> Eq for Identity<'a> {}
+
+impl<'a> Hash for Identity<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id_text().hash(state)
+    }
+}
+
+/// Whether `text` is one of the DOT keywords `node`, `edge`, `graph`, `digraph`, `subgraph` and
+/// `strict`, in any letter case, which can only be used as ids when quoted.
+pub(crate) const fn is_keyword(text: &str) -> bool {
+    const KEYWORDS: [&str; 6] = ["node", "edge", "graph", "digraph", "subgraph", "strict"];
+    let text = text.as_bytes();
+    let mut k = 0;
+    while k < KEYWORDS.len() {
+        let keyword = KEYWORDS[k].as_bytes();
+        if keyword.len() == text.len() {
+            let mut i = 0;
+            while i < text.len() && text[i].to_ascii_lowercase() == keyword[i] {
+                i += 1;
+            }
+            if i == text.len() {
+                return true;
+            }
+        }
+        k += 1;
+    }
+    false
+}
+
+/// The compiled id pattern, built once on first use and shared afterwards.
+pub(crate) fn id_pattern() -> &'static regex::Regex {
+    static PATTERN: &str = r#"^[\p{Alphabetic}_][\p{Alphabetic}\p{M}\d_]*$"#;
+    static REGEX: OnceLock<regex::Regex> = OnceLock::new();
+    REGEX.get_or_init(|| regex::Regex::new(PATTERN).unwrap())
+}
+
+/// The dot numeral syntax `[-]?(.[0-9]+ | [0-9]+(.[0-9]*)?)`, compiled once like `id_pattern`.
+pub(crate) fn numeral_pattern() -> &'static regex::Regex {
+    static PATTERN: &str = r#"^-?(\.\d+|\d+(\.\d*)?)$"#;
+    static REGEX: OnceLock<regex::Regex> = OnceLock::new();
+    REGEX.get_or_init(|| regex::Regex::new(PATTERN).unwrap())
+}
+
+impl<'a> Identity<'a> {
+    /// Create an identity that never fails: an unquoted string when `data` is a valid id or
+    /// numeral, a quoted string otherwise. The dot keywords (`node`, `edge`, `graph`, `digraph`,
+    /// `subgraph` and `strict`, in any case) are quoted as well, since they cannot be used as ids.
+    ///
+    /// Strings that only look numeric, like versions (`1.2.3`) or exponents (`1e10`), are not
+    /// numerals and get quoted. Numerals with leading zeros (`0123`) are quoted too, since tools
+    /// reading the output as numbers would drop the zeros.
+    /// ```
+    /// use tabbycat::Identity;
+    /// assert_eq!("foo", Identity::auto("foo").to_string());
+    /// assert_eq!("\"foo bar\"", Identity::auto("foo bar").to_string());
+    /// assert_eq!("-1.5", Identity::auto("-1.5").to_string());
+    /// assert_eq!("\"node\"", Identity::auto("node").to_string());
+    /// assert_eq!("\"0123\"", Identity::auto("0123").to_string());
+    /// ```
+    pub fn auto(data: &'a str) -> Self {
+        let keyword = is_keyword(data);
+        let digits = data.strip_prefix('-').unwrap_or(data).as_bytes();
+        let leading_zero = digits.len() > 1 && digits[0] == b'0' && digits[1].is_ascii_digit();
+        let numeral = numeral_pattern().is_match(data) && !leading_zero;
+        if !keyword && (id_pattern().is_match(data) || numeral) {
+            Identity::String(data)
+        } else {
+            Identity::Quoted(data)
+        }
+    }
+    /// The text of the identity without the surrounding quotes of quoted strings.
+    pub(crate) fn unquoted(&self) -> String {
+        match self {
+            Identity::Quoted(s) => s.to_string(),
+            Identity::OwnedQuoted(s) | Identity::EscString(s) => s.clone(),
+            other => other.to_string(),
+        }
+    }
+    /// The string content of the identity, without quotes, if it holds one: `String`, `Quoted`,
+    /// `Owned`, `OwnedQuoted` and `EscString` return their text, all other variants `None`.
+    /// ```
+    /// use tabbycat::Identity;
+    /// assert_eq!(Some("foo bar"), Identity::quoted("foo bar").as_str());
+    /// assert_eq!(None, Identity::from(1).as_str());
+    /// ```
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Identity::String(s) | Identity::Quoted(s) => Some(s),
+            Identity::Owned(s) | Identity::OwnedQuoted(s) | Identity::EscString(s) => Some(s),
+            _ => None,
+        }
+    }
+    /// create a checked id string, the lexical rule is:
+    /// `^[\p{Alphabetic}_][\p{Alphabetic}\p{M}\d_]*$`
+    ///
+    /// Graphviz accepts any non-ASCII character in an id; letters of every script (with their
+    /// combining marks) are allowed here, while whitespace and punctuation still need quoting.
+    pub fn id(data: &'a str) -> std::result::Result<Self, Error> {
+        Identity::try_from(data)
+    }
+    /// create a checked number, rejecting `NaN` and infinities which are not valid dot numerals.
+    /// (`Identity::from` accepts them and renders them as the quoted strings `"NaN"`, `"inf"` and `"-inf"`.)
+    pub fn finite(number: f64) -> std::result::Result<Self, Error> {
+        if number.is_finite() {
+            Ok(Identity::Double(number))
+        } else {
+            Err(Error::NonFiniteNumber(number))
+        }
+    }
+    /// create a quoted string. Quotes and backslashes are escaped and line breaks are written as
+    /// `\n`, so graphviz shows the text as it is; see `Identity::label_template` for labels with
+    /// placeholders like `\N`.
+    /// ```
+    /// use tabbycat::Identity;
+    /// assert_eq!(r#""C:\\temp""#, Identity::quoted(r"C:\temp").to_string());
+    /// assert_eq!(r#""node \\N""#, Identity::quoted(r"node \N").to_string());
+    /// ```
+    pub fn quoted(data: &'a str) -> Self {
+        Identity::Quoted(data)
+    }
+    /// The text written for this identity without surrounding quotes, which `PartialEq` and `Hash`
+    /// go by: the content of unquoted strings and of `EscString`, the escaped content of quoted
+    /// strings, and the rendered form of all other variants.
+    fn id_text(&self) -> Cow<'_, str> {
+        match self {
+            Identity::Quoted(text) => escape_quoted(text),
+            Identity::OwnedQuoted(text) => escape_quoted(text),
+            _ => match self.as_str() {
+                Some(text) => Cow::Borrowed(text),
+                None => {
+                    let text = self.to_string();
+                    match text.strip_prefix('"').and_then(|text| text.strip_suffix('"')) {
+                        Some(inner) => Cow::Owned(inner.to_string()),
+                        None => Cow::Owned(text),
+                    }
+                }
+            },
+        }
+    }
+    /// A label in which graphviz expands the placeholders `\N`, `\G`, `\E`, `\H`, `\T` and `\L`
+    /// (node, graph, edge, head, tail and object label). Quotes and line breaks are escaped like in
+    /// `Identity::quoted`, `\\` stays a literal backslash and any other backslash is doubled.
+    /// ```
+    /// use tabbycat::Identity;
+    /// assert_eq!(r#""node \N""#, Identity::label_template(r"node \N").to_string());
+    /// assert_eq!(r#""C:\\temp, not \\N""#, Identity::label_template(r"C:\temp, not \\N").to_string());
+    /// ```
+    pub fn label_template(text: &str) -> Self {
+        let mut escaped = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => escaped.push_str("\\\""),
+                '\\' => match chars.peek() {
+                    Some(&next @ ('N' | 'G' | 'E' | 'H' | 'T' | 'L' | '\\')) => {
+                        chars.next();
+                        escaped.push('\\');
+                        escaped.push(next);
+                    }
+                    _ => escaped.push_str("\\\\"),
+                },
+                '\n' => escaped.push_str("\\n"),
+                c => escaped.push(c),
+            }
+        }
+        Identity::EscString(escaped)
+    }
+}
+
+/// Checked conversion with the same lexical rule as `Identity::id`:
+/// ```
+/// use std::convert::TryFrom;
+/// use tabbycat::{Error, Identity};
+/// assert_eq!(Ok(Identity::String("a_1")), Identity::try_from("a_1"));
+/// assert_eq!(Err(Error::InvalidIdentity("1a".to_string())), Identity::try_from("1a"));
+/// ```
+impl<'a> TryFrom<&'a str> for Identity<'a> {
+    type Error = Error;
+
+    fn try_from(data: &'a str) -> std::result::Result<Self, Self::Error> {
+        if id_pattern().is_match(data) {
+            Ok(Identity::String(data))
+        } else {
+            Err(Error::InvalidIdentity(data.to_string()))
+        }
+    }
+}
+
+impl<'a> Port<'a> {
+    /// corresponds to `:id`
+    pub fn id(i: Identity<'a>) -> Self {
+        Port::ID(i, None)
+    }
+    /// corresponds to `:id:<direction>`
+    pub fn id_compass(i: Identity<'a>, c: Compass) -> Self {
+        Port::ID(i, Some(c))
+    }
+    /// corresponds to `:<direction>`
+    pub fn compass(c: Compass) -> Self {
+        Port::Compass(c)
+    }
+    /// corresponds to `:name`, naming a record field; the name is quoted only when it is not a valid id
+    pub fn field(name: &'a str) -> Self {
+        Port::ID(Identity::auto(name), None)
+    }
+    /// corresponds to `:name:<direction>`, see `Port::field`
+    pub fn field_compass(name: &'a str, c: Compass) -> Self {
+        Port::ID(Identity::auto(name), Some(c))
+    }
+}
+
+/// The content of a dot quoted string for `text`. Quotes and backslashes are escaped and line breaks
+/// become `\n`; everything else, non-ASCII included, is kept as it is. Every backslash is doubled, so
+/// graphviz shows it literally instead of reading an escape sequence like `\N`.
+fn escape_quoted(text: &str) -> Cow<'_, str> {
+    if !text.contains(['"', '\\', '\n']) {
+        return Cow::Borrowed(text);
+    }
+    let mut escaped = String::with_capacity(text.len() + 2);
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Write `text` as a dot quoted string, escaped by `escape_quoted`.
+fn write_quoted(f: &mut Formatter<'_>, text: &str) -> Result {
+    write!(f, "\"{}\"", escape_quoted(text))
+}
+
+/// Write `text` as `//` comments, one per line, or as a single `/* ... */` comment.
+fn write_comment(f: &mut Formatter<'_>, text: &str, line: bool) -> Result {
+    if line {
+        text.split('\n').enumerate().try_for_each(|(i, line)| {
+            if i > 0 {
+                writeln!(f)
+            } else {
+                Ok(())
+            }.and(if line.is_empty() {
+                write!(f, "//")
+            } else {
+                write!(f, "// {}", line)
+            })
+        })
+    } else {
+        write!(f, "/* {} */", text.replace("*/", "* /"))
+    }
+}
+
+/// Write a statement list as a braced block with one statement per line, each indented by `indent` spaces.
+/// Nested blocks are rendered with the same width, so their indentation accumulates.
+/// An empty list is written as `{}`.
+fn write_block(f: &mut Formatter<'_>, stmts: &StmtList, indent: usize) -> Result {
+    let buffer = format!("{:width$}", stmts, width = indent);
+    let body = buffer.trim();
+    if body.is_empty() {
+        return write!(f, "{{}}");
+    }
+    writeln!(f, "{{").and(
+        body.split('\n').try_for_each(|y| {
+            write!(f, "{}", " ".repeat(indent))
+                .and(writeln!(f, "{}", y))
+        }).and(write!(f, "}}"))
+    )
+}
+
+impl<'a> Graph<'a> {
+    /// Build a graph without id that has one edge statement per pair, using `->` in a digraph and
+    /// `--` in a graph. Nodes are only mentioned by the edges, there are no node statements.
+    /// ```
+    /// use tabbycat::{Graph, GraphType, Identity};
+    /// let id = |name| Identity::id(name).unwrap();
+    /// let graph = Graph::from_edges(GraphType::Graph, vec![(id("a"), id("b")), (id("b"), id("c"))]);
+    /// assert_eq!("graph {a--b;b--c;}", graph.to_string());
+    /// ```
+    pub fn from_edges<I: IntoIterator<Item=(Identity<'a>, Identity<'a>)>>(graph_type: GraphType, edges: I) -> Self {
+        let op = match graph_type {
+            GraphType::Graph => EdgeOp::Line,
+            GraphType::DiGraph => EdgeOp::Arrow,
+        };
+        let stmts = edges.into_iter().fold(StmtList::new(), |stmts, (a, b)| stmts.add_edge_between(a, b, op));
+        Graph { graph_type, strict: false, id: None, stmts, header: None }
+    }
+
+    /// Whether this is a `graph` or a `digraph`
+    pub fn graph_type(&self) -> GraphType {
+        self.graph_type
+    }
+
+    /// Whether the graph is `strict`
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// The id of the graph, if any
+    pub fn id(&self) -> Option<&Identity<'a>> {
+        self.id.as_ref()
+    }
+
+    /// The top-level statements of the graph
+    pub fn stmts(&self) -> &StmtList<'a> {
+        &self.stmts
+    }
+
+    /// The top-level statements of the graph, to edit them after the graph was built
+    pub fn stmts_mut(&mut self) -> &mut StmtList<'a> {
+        &mut self.stmts
+    }
+
+    /// Append the statements of `other` to this graph, e.g. to assemble a graph from fragments
+    /// built independently. The id and header of this graph are kept. Fails when the graphs
+    /// disagree on being directed or strict.
+    pub fn merge(mut self, other: Graph<'a>) -> std::result::Result<Graph<'a>, Error> {
+        if self.graph_type != other.graph_type || self.strict != other.strict {
+            return Err(Error::IncompatibleGraphs {
+                graph_type: self.graph_type,
+                strict: self.strict,
+                other_graph_type: other.graph_type,
+                other_strict: other.strict,
+            });
+        }
+        self.stmts = self.stmts.merge(other.stmts);
+        Ok(self)
+    }
+
+    /// Render the graph with the attributes of every bracket sorted by key, see `AttrList::sorted`,
+    /// so the output does not depend on the order the attributes were added in, e.g. from a `HashMap`.
+    pub fn to_string_sorted(&self) -> String {
+        let mut graph = self.clone();
+        for_each_attr_list(&mut graph.stmts, &mut AttrList::sort_in_place);
+        graph.to_string()
+    }
+    /// Merge the brackets of every attribute list into one, see `AttrList::flattened`, so graphs
+    /// that only differ in how their attributes were grouped render the same. Empty brackets are
+    /// dropped; combine with `to_string_sorted` to ignore the attribute order too.
+    #[must_use]
+    pub fn canonicalize(mut self) -> Graph<'a> {
+        for_each_attr_list(&mut self.stmts, &mut AttrList::flatten_in_place);
+        self
+    }
+
+    /// Render the graph with one statement per line, indenting the body of the graph
+    /// and of every nested subgraph by `indent` spaces per level. Within each block the attribute
+    /// lists of nodes, single-line edges and `node`/`edge`/`graph` statements are aligned to one
+    /// column, and attribute pairs are separated by `, `.
+    /// This is the same output as the alternate format `{:#width$}`.
+    /// ```
+    /// use tabbycat::{AttrList, AttrType, Edge, GraphBuilder, GraphType, Identity, StmtList};
+    /// let id = |name| Identity::id(name).unwrap();
+    /// let attrs = |k, v| AttrList::new().add(id(k), id(v));
+    /// let graph = GraphBuilder::default()
+    ///     .graph_type(GraphType::DiGraph)
+    ///     .strict(false)
+    ///     .id(id("G"))
+    ///     .stmts(StmtList::new()
+    ///         .add_attr(AttrType::Node, attrs("shape", "box"))
+    ///         .add_node(id("start"), None, Some(attrs("color", "red")))
+    ///         .add_edge(Edge::head_node(id("start"), None).arrow_to_node(id("b"), None).add_attrlist(attrs("style", "bold"))))
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!("digraph G {\n  node     [shape=box];\n  start    [color=red];\n  start->b [style=bold];\n}",
+    ///            graph.to_string_pretty(2));
+    /// ```
+    pub fn to_string_pretty(&self, indent: usize) -> String {
+        format!("{:#width$}", self, width = indent)
+    }
+
+    /// Use `font` at `size` points for every label in the graph. Graphviz does not cascade
+    /// graph-level font attributes to nodes and edges, so `fontname` and `fontsize` are set as
+    /// graph attributes and as node and edge defaults, placed before all other statements.
+    #[must_use]
+    pub fn with_typography(mut self, font: &'a str, size: f64) -> Graph<'a> {
+        let fonts = AttrList::new()
+            .add(Identity::String("fontname"), Identity::Quoted(font))
+            .add(Identity::String("fontsize"), Identity::Double(size));
+        let defaults = [AttrType::Graph, AttrType::Node, AttrType::Edge].iter()
+            .map(|attr_type| Stmt::Attr(*attr_type, fonts.clone()));
+        self.stmts.0.splice(0..0, defaults);
+        self
+    }
+
+    /// Write the graph into `w` without first building the whole document as a `String`.
+    /// The output is identical to `to_string()`; every fragment produced by the `Display`
+    /// implementation is passed straight to the writer, so wrap files in a `BufWriter`.
+    pub fn render_to<W: std::io::Write>(&self, w: &mut W) -> std::io::Result<()> {
+        write!(w, "{}", self)
+    }
+
+    /// Append the graph to `buf`, with the same output as `to_string()`. Capacity for the whole
+    /// graph is reserved up front from the number of statements, so graphs of short statements
+    /// are written without growing the buffer again; longer statements still grow it, just less often.
+    pub fn write_fmt_into(&self, buf: &mut String) {
+        use std::fmt::Write;
+        buf.reserve(self.estimated_len());
+        write!(buf, "{}", self).expect("writing into a String does not fail");
+    }
+
+    /// A rough length of the compact output: the header plus a short node or edge per statement.
+    pub(crate) fn estimated_len(&self) -> usize {
+        const HEADER_LEN: usize = 32;
+        const STMT_LEN: usize = 16;
+        HEADER_LEN + STMT_LEN * self.walk_stmts().count()
+    }
+}
+
+impl<'a> Graph<'a> {
+    /// Set an `id` attribute on every node and edge, which graphviz carries over to the
+    /// rendered SVG elements. Nodes are named `<prefix><node id>`, edges `<prefix><tail>-<head>`
+    /// following all endpoints of the edge. Characters other than letters, digits and `_` in node
+    /// ids are replaced by `_`, so the ids can be used in CSS selectors. An id that is already taken,
+    /// e.g. by a repeated edge, gets a `_2`, `_3`, ... suffix in statement order, so the result is
+    /// deterministic and unique.
+    ///
+    /// Nodes that only appear in edges get a node statement appended to the graph to carry their id.
+    #[must_use]
+    pub fn assign_element_ids(mut self, prefix: &str) -> Graph<'a> {
+        let mut state = ElementIds {
+            prefix,
+            nodes: HashMap::new(),
+            taken: HashSet::new(),
+            declared: HashSet::new(),
+            referenced: Vec::new(),
+        };
+        state.visit(&mut self.stmts);
+        for id in std::mem::take(&mut state.referenced) {
+            if state.declared.insert(id.clone()) {
+                let value = state.node_id(&id);
+                self.stmts.0.push(Stmt::Node {
+                    id,
+                    port: None,
+                    attr: Some(AttrList::new().add(Identity::String("id"), value)),
+                });
+            }
+        }
+        self
+    }
+}
+
+impl<'a> Graph<'a> {
+    /// Merge `attrs` into the attribute list of every node in `nodes`, for example to outline
+    /// search results with `penwidth`, `peripheries` and `color`. Node statements are updated
+    /// wherever they appear, including inside subgraphs; a node that is only referenced by edges
+    /// gets a new node statement appended to the graph. Nodes not present in the graph are ignored.
+    #[must_use]
+    pub fn emphasize_nodes(mut self, nodes: &HashSet<Identity<'a>>, attrs: AttrList<'a>) -> Graph<'a> {
+        let mut declared = HashSet::new();
+        let mut referenced = Vec::new();
+        emphasize(&mut self.stmts, nodes, &attrs, &mut declared, &mut referenced);
+        for id in referenced {
+            if declared.insert(id.clone()) {
+                self.stmts.0.push(Stmt::Node { id, port: None, attr: Some(attrs.clone()) });
+            }
+        }
+        self
+    }
+}
+
+impl<'a> Graph<'a> {
+    /// Extract the nodes within `radius` edges of `node`, following edges in both directions, into a
+    /// standalone graph, e.g. to share a minimal reproduction of a rendering problem.
+    ///
+    /// The result keeps the type, strictness and id of the graph, the node statements of the selected
+    /// nodes and the edges between them, together with the attribute statements and equations of every
+    /// scope that still has content, so the same defaults apply. Subgraphs keep their place in the tree.
+    /// An edge chain that leaves the neighborhood is cut down to its segments inside of it; a segment with
+    /// a subgraph endpoint is kept only if every node of that subgraph is selected.
+    pub fn neighborhood(&self, node: &Identity<'a>, radius: usize) -> Graph<'a> {
+        let mut adjacent: HashMap<Identity<'a>, HashSet<Identity<'a>>> = HashMap::new();
+        collect_adjacency(&self.stmts, &mut adjacent);
+        let mut selected = HashSet::new();
+        let mut queue = VecDeque::new();
+        selected.insert(node.clone());
+        queue.push_back((node.clone(), 0));
+        while let Some((current, distance)) = queue.pop_front() {
+            if distance == radius {
+                continue;
+            }
+            for next in adjacent.get(&current).into_iter().flatten() {
+                if selected.insert(next.clone()) {
+                    queue.push_back((next.clone(), distance + 1));
+                }
+            }
+        }
+        Graph {
+            graph_type: self.graph_type,
+            strict: self.strict,
+            id: self.id.clone(),
+            stmts: select_stmts(&self.stmts, &selected),
+            header: self.header.clone(),
+        }
+    }
+}
+
+impl<'a> Graph<'a> {
+    /// Collapse duplicate statements the way graphviz does for a `strict` graph: repeated node
+    /// statements and repeated single-segment edges between the same two nodes are merged into the
+    /// first occurrence, which takes the attribute list of the last occurrence that has one. Edges are matched in
+    /// their direction in a digraph and in either direction in an undirected graph; ports are ignored.
+    ///
+    /// Every statement list (the graph and each subgraph) is deduplicated on its own, so that cluster
+    /// membership is unchanged. Edge chains and edges with subgraph endpoints are kept as they are.
+    #[must_use]
+    pub fn dedup_strict(mut self) -> Graph<'a> {
+        dedup_list(&mut self.stmts, matches!(self.graph_type, GraphType::DiGraph));
+        self
+    }
+}
+
+/// Replace the attribute list of a duplicate statement with a later one, unless that has none.
+fn keep_last_attr<'a>(target: &mut Option<AttrList<'a>>, later: Option<AttrList<'a>>) {
+    if later.is_some() {
+        *target = later;
+    }
+}
+
+fn dedup_list(list: &mut StmtList<'_>, directed: bool) {
+    let mut nodes = HashMap::new();
+    let mut edges = HashMap::new();
+    let mut stmts: Vec<Stmt> = Vec::with_capacity(list.0.len());
+    for stmt in std::mem::take(&mut list.0) {
+        match stmt {
+            Stmt::Node { id, port, attr } => match nodes.get(&id) {
+                Some(&index) => if let Stmt::Node { attr: first, .. } = &mut stmts[index] {
+                    keep_last_attr(first, attr);
+                },
+                None => {
+                    nodes.insert(id.clone(), stmts.len());
+                    stmts.push(Stmt::Node { id, port, attr });
+                }
+            },
+            Stmt::Edge(mut edge) => {
+                let key = match (&edge.node, edge.body.as_slice()) {
+                    (EdgeNode::Node { id: tail, .. }, [EdgeBody { node: EdgeNode::Node { id: head, .. }, .. }]) =>
+                        if directed || tail.unquoted() <= head.unquoted() {
+                            Some((tail.clone(), head.clone()))
+                        } else {
+                            Some((head.clone(), tail.clone()))
+                        },
+                    _ => None,
+                };
+                match key.as_ref().and_then(|key| edges.get(key)) {
+                    Some(&index) => if let Stmt::Edge(first) = &mut stmts[index] {
+                        keep_last_attr(&mut first.attr, edge.attr.take());
+                    },
+                    None => {
+                        if let Some(key) = key {
+                            edges.insert(key, stmts.len());
+                        }
+                        stmts.push(Stmt::Edge(edge));
+                    }
+                }
+            }
+            Stmt::SubGraph(mut sub) => {
+                dedup_list(sub.stmts_mut(), directed);
+                stmts.push(Stmt::SubGraph(sub));
+            }
+            other => stmts.push(other),
+        }
+    }
+    list.0 = stmts;
+}
+
+/// Depth-first iterator over the statements of a graph, created by `Graph::walk_stmts`.
+pub struct WalkStmts<'b, 'a> {
+    stack: Vec<std::slice::Iter<'b, Stmt<'a>>>,
+}
+
+impl<'b, 'a> Iterator for WalkStmts<'b, 'a> {
+    type Item = &'b Stmt<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let stmt = match self.stack.last_mut()?.next() {
+                Some(stmt) => stmt,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            match stmt {
+                Stmt::SubGraph(sub) => self.stack.push(sub.stmts().0.iter()),
+                Stmt::Edge(edge) => {
+                    let subgraphs = std::iter::once(&edge.node)
+                        .chain(edge.body.iter().map(|body| &body.node))
+                        .filter_map(|node| match node {
+                            EdgeNode::SubGraph(sub) => Some(sub.stmts().0.iter()),
+                            EdgeNode::Node { .. } => None,
+                        })
+                        .collect::<Vec<_>>();
+                    self.stack.extend(subgraphs.into_iter().rev());
+                }
+                _ => {}
+            }
+            return Some(stmt);
+        }
+    }
+}
+
+impl<'a> Graph<'a> {
+    /// Iterate over every statement of the graph, depth first: a subgraph statement is followed by the
+    /// statements inside of it, and an edge by the statements of the subgraphs used as its endpoints.
+    pub fn walk_stmts(&self) -> WalkStmts<'_, 'a> {
+        WalkStmts { stack: vec![self.stmts.0.iter()] }
+    }
+    /// Iterate over every edge statement, including those in subgraphs and in subgraph endpoints,
+    /// in the depth first order of `walk_stmts`: an edge comes right before the edges inside its
+    /// subgraph endpoints, and the edges of a subgraph come before the statements following it.
+    pub fn edges_iter(&self) -> impl Iterator<Item=&Edge<'a>> {
+        self.walk_stmts().filter_map(|stmt| match stmt {
+            Stmt::Edge(edge) => Some(edge),
+            _ => None,
+        })
+    }
+    /// Iterate over the ids of all node statements, in the same order as `edges_iter`.
+    /// Nodes that are only referenced by edges are not included, and a node declared several
+    /// times is yielded each time.
+    pub fn nodes_iter(&self) -> impl Iterator<Item=&Identity<'a>> {
+        self.walk_stmts().filter_map(|stmt| match stmt {
+            Stmt::Node { id, .. } => Some(id),
+            _ => None,
+        })
+    }
+    /// The number of distinct nodes, including nodes that are only referenced by edges.
+    pub fn node_count(&self) -> usize {
+        let mut nodes = HashSet::new();
+        for stmt in self.walk_stmts() {
+            match stmt {
+                Stmt::Node { id, .. } => {
+                    nodes.insert(id);
+                }
+                Stmt::Edge(edge) => {
+                    for node in std::iter::once(&edge.node).chain(edge.body.iter().map(|body| &body.node)) {
+                        if let EdgeNode::Node { id, .. } = node {
+                            nodes.insert(id);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        nodes.len()
+    }
+    /// The number of edges graphviz creates: a chain `a->b->c` counts two edges, and an edge between
+    /// subgraphs counts one edge per pair of nodes, e.g. three for `a->{b c d}`. Duplicates are counted.
+    pub fn edge_count(&self) -> usize {
+        self.walk_stmts()
+            .map(|stmt| match stmt {
+                Stmt::Edge(edge) => {
+                    let mut from = &edge.node;
+                    let mut count = 0;
+                    for body in edge.body.iter() {
+                        let (mut tails, mut heads) = (Vec::new(), Vec::new());
+                        endpoint_ids(from, &mut tails);
+                        endpoint_ids(&body.node, &mut heads);
+                        count += tails.len() * heads.len();
+                        from = &body.node;
+                    }
+                    count
+                }
+                _ => 0,
+            })
+            .sum()
+    }
+    /// The attribute list of the first node statement for `id` that has one, searching the statements
+    /// in the order of `walk_stmts`, so nodes inside subgraphs and clusters are found too.
+    /// When a node is declared several times graphviz merges all of its attributes, but only the
+    /// first attribute list is returned here; statements without attributes are skipped.
+    pub fn node_attrs(&self, id: &Identity) -> Option<&AttrList<'a>> {
+        self.walk_stmts().find_map(|stmt| match stmt {
+            Stmt::Node { id: node, attr: Some(attr), .. } if node == id => Some(attr),
+            _ => None,
+        })
+    }
+}
+
+/// All node ids mentioned by an edge endpoint, including every node inside a subgraph endpoint.
+pub(crate) fn endpoint_ids<'a>(node: &EdgeNode<'a>, ids: &mut Vec<Identity<'a>>) {
+    fn list_ids<'a>(list: &StmtList<'a>, ids: &mut Vec<Identity<'a>>) {
+        for stmt in list.0.iter() {
+            match stmt {
+                Stmt::Node { id, .. } => ids.push(id.clone()),
+                Stmt::Edge(edge) => {
+                    endpoint_ids(&edge.node, ids);
+                    edge.body.iter().for_each(|body| endpoint_ids(&body.node, ids));
+                }
+                Stmt::SubGraph(sub) => list_ids(sub.stmts(), ids),
+                Stmt::Attr(..) | Stmt::Equation(..) | Stmt::Comment(..) | Stmt::LineComment(..) => {}
+            }
+        }
+    }
+    match node {
+        EdgeNode::Node { id, .. } => ids.push(id.clone()),
+        EdgeNode::SubGraph(sub) => list_ids(sub.stmts(), ids),
     }
 }
 
-impl<'a> From<i16> for Identity<'a> {
-    fn from(number: i16) -> Self {
-        Identity::I16(number)
+fn collect_adjacency<'a>(list: &StmtList<'a>, adjacent: &mut HashMap<Identity<'a>, HashSet<Identity<'a>>>) {
+    for stmt in list.0.iter() {
+        match stmt {
+            Stmt::Edge(edge) => {
+                let mut from = &edge.node;
+                for body in edge.body.iter() {
+                    let (mut tails, mut heads) = (Vec::new(), Vec::new());
+                    endpoint_ids(from, &mut tails);
+                    endpoint_ids(&body.node, &mut heads);
+                    for tail in tails.iter() {
+                        for head in heads.iter() {
+                            adjacent.entry(tail.clone()).or_default().insert(head.clone());
+                            adjacent.entry(head.clone()).or_default().insert(tail.clone());
+                        }
+                    }
+                    from = &body.node;
+                }
+                if let EdgeNode::SubGraph(sub) = &edge.node {
+                    collect_adjacency(sub.stmts(), adjacent);
+                }
+                for body in edge.body.iter() {
+                    if let EdgeNode::SubGraph(sub) = &body.node {
+                        collect_adjacency(sub.stmts(), adjacent);
+                    }
+                }
+            }
+            Stmt::SubGraph(sub) => collect_adjacency(sub.stmts(), adjacent),
+            Stmt::Node { .. } | Stmt::Attr(..) | Stmt::Equation(..) | Stmt::Comment(..) | Stmt::LineComment(..) => {}
+        }
     }
 }
 
-impl<'a> From<u32> for Identity<'a> {
-    fn from(number: u32) -> Self {
-        Identity::U32(number)
+/// Filter a statement list down to the selected nodes, returning an empty list if nothing but
+/// attribute statements and equations would remain.
+fn select_stmts<'a>(list: &StmtList<'a>, selected: &HashSet<Identity<'a>>) -> StmtList<'a> {
+    let inside = |node: &EdgeNode<'a>| {
+        let mut ids = Vec::new();
+        endpoint_ids(node, &mut ids);
+        ids.iter().all(|id| selected.contains(id))
+    };
+    let mut stmts = Vec::new();
+    let mut content = false;
+    for stmt in list.0.iter() {
+        match stmt {
+            Stmt::Attr(..) | Stmt::Equation(..) | Stmt::Comment(..) | Stmt::LineComment(..) => stmts.push(stmt.clone()),
+            Stmt::Node { id, .. } => if selected.contains(id) {
+                content = true;
+                stmts.push(stmt.clone());
+            },
+            Stmt::Edge(edge) => {
+                if inside(&edge.node) && edge.body.iter().all(|body| inside(&body.node)) {
+                    content = true;
+                    stmts.push(stmt.clone());
+                    continue;
+                }
+                let mut from = &edge.node;
+                for body in edge.body.iter() {
+                    if inside(from) && inside(&body.node) {
+                        content = true;
+                        stmts.push(Stmt::Edge(Edge {
+                            node: from.clone(),
+                            body: vec![body.clone()],
+                            attr: edge.attr.clone(),
+                        }));
+                    }
+                    from = &body.node;
+                }
+            }
+            Stmt::SubGraph(sub) => {
+                let inner = select_stmts(sub.stmts(), selected);
+                if !inner.0.is_empty() {
+                    content = true;
+                    stmts.push(Stmt::SubGraph(match sub {
+                        SubGraph::SubGraph { id, .. } => SubGraph::subgraph(id.clone(), inner),
+                        SubGraph::Cluster(_) => SubGraph::cluster(inner),
+                    }));
+                }
+            }
+        }
+    }
+    if !content {
+        stmts.clear();
     }
+    StmtList(stmts)
 }
 
-impl<'a> From<i32> for Identity<'a> {
-    fn from(number: i32) -> Self {
-        Identity::I32(number)
+fn emphasize<'a>(list: &mut StmtList<'a>, nodes: &HashSet<Identity<'a>>, attrs: &AttrList<'a>,
+                 declared: &mut HashSet<Identity<'a>>, referenced: &mut Vec<Identity<'a>>) {
+    for stmt in list.0.iter_mut() {
+        match stmt {
+            Stmt::Node { id, attr, .. } if nodes.contains(id) => {
+                declared.insert(id.clone());
+                let merged = match attr.take() {
+                    Some(list) => list.merge(attrs.clone()),
+                    None => attrs.clone(),
+                };
+                attr.replace(merged);
+            }
+            Stmt::Edge(edge) => {
+                emphasize_endpoint(&mut edge.node, nodes, attrs, declared, referenced);
+                for body in edge.body.iter_mut() {
+                    emphasize_endpoint(&mut body.node, nodes, attrs, declared, referenced);
+                }
+            }
+            Stmt::SubGraph(SubGraph::SubGraph { stmts, .. }) | Stmt::SubGraph(SubGraph::Cluster(stmts)) =>
+                emphasize(stmts, nodes, attrs, declared, referenced),
+            _ => {}
+        }
     }
 }
 
-impl<'a> From<u64> for Identity<'a> {
-    fn from(number: u64) -> Self {
-        Identity::U64(number)
+/// Apply `f` to every attribute list in `list`, including those inside subgraphs.
+fn for_each_attr_list<'a>(list: &mut StmtList<'a>, f: &mut dyn FnMut(&mut AttrList<'a>)) {
+    for stmt in list.0.iter_mut() {
+        match stmt {
+            Stmt::Node { attr: Some(attr), .. } => f(attr),
+            Stmt::Attr(_, attr) => f(attr),
+            Stmt::Edge(edge) => {
+                if let Some(attr) = &mut edge.attr {
+                    f(attr);
+                }
+                for node in std::iter::once(&mut edge.node).chain(edge.body.iter_mut().map(|body| &mut body.node)) {
+                    if let EdgeNode::SubGraph(sub) = node {
+                        for_each_attr_list(sub.stmts_mut(), f);
+                    }
+                }
+            }
+            Stmt::SubGraph(sub) => for_each_attr_list(sub.stmts_mut(), f),
+            _ => {}
+        }
     }
 }
 
-impl<'a> From<i64> for Identity<'a> {
-    fn from(number: i64) -> Self {
-        Identity::I64(number)
+fn emphasize_endpoint<'a>(node: &mut EdgeNode<'a>, nodes: &HashSet<Identity<'a>>, attrs: &AttrList<'a>,
+                          declared: &mut HashSet<Identity<'a>>, referenced: &mut Vec<Identity<'a>>) {
+    match node {
+        EdgeNode::Node { id, .. } => if nodes.contains(id) {
+            referenced.push(id.clone())
+        },
+        EdgeNode::SubGraph(SubGraph::SubGraph { stmts, .. }) | EdgeNode::SubGraph(SubGraph::Cluster(stmts)) =>
+            emphasize(stmts, nodes, attrs, declared, referenced),
     }
 }
 
-impl<'a> From<i128> for Identity<'a> {
-    fn from(number: i128) -> Self {
-        Identity::I128(number)
-    }
+struct ElementIds<'p, 'a> {
+    prefix: &'p str,
+    /// The element id given to each node
+    nodes: HashMap<Identity<'a>, String>,
+    /// Every element id handed out so far
+    taken: HashSet<String>,
+    declared: HashSet<Identity<'a>>,
+    referenced: Vec<Identity<'a>>,
 }
 
+impl<'p, 'a> ElementIds<'p, 'a> {
+    /// The text of an id with every character other than letters, digits and `_` replaced by `_`,
+    /// so it can be used in SVG and CSS selectors as it is.
+    fn text(id: &Identity) -> String {
+        let text = match id {
+            Identity::String(s) | Identity::Quoted(s) => s.to_string(),
+            Identity::Owned(s) | Identity::OwnedQuoted(s) | Identity::EscString(s) => s.clone(),
+            other => other.to_string(),
+        };
+        text.chars().map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' }).collect()
+    }
 
-impl<'a> From<u128> for Identity<'a> {
-    fn from(number: u128) -> Self {
-        Identity::U128(number)
+    /// `<prefix><name>`, or with a `_2`, `_3`, ... suffix when that is already taken.
+    fn unique(&mut self, name: &str) -> String {
+        let base = format!("{}{}", self.prefix, name);
+        let mut value = base.clone();
+        let mut count = 1;
+        while self.taken.contains(&value) {
+            count += 1;
+            value = format!("{}_{}", base, count);
+        }
+        self.taken.insert(value.clone());
+        value
     }
-}
 
-impl<'a> From<f32> for Identity<'a> {
-    fn from(number: f32) -> Self {
-        Identity::Float(number)
+    fn node_id(&mut self, id: &Identity<'a>) -> Identity<'a> {
+        let value = match self.nodes.get(id) {
+            Some(value) => value.clone(),
+            None => {
+                let value = self.unique(&Self::text(id));
+                self.nodes.insert(id.clone(), value.clone());
+                value
+            }
+        };
+        Identity::OwnedQuoted(value)
     }
-}
 
-impl<'a> From<f64> for Identity<'a> {
-    fn from(number: f64) -> Self {
-        Identity::Double(number)
+    fn endpoint(&mut self, node: &mut EdgeNode<'a>) -> String {
+        match node {
+            EdgeNode::Node { id, .. } => {
+                self.referenced.push(id.clone());
+                Self::text(id)
+            }
+            EdgeNode::SubGraph(sub) => {
+                let name = match sub {
+                    SubGraph::SubGraph { id: Some(id), .. } => Self::text(id),
+                    _ => "subgraph".to_string(),
+                };
+                self.visit_subgraph(sub);
+                name
+            }
+        }
     }
-}
 
-impl<'a> Identity<'a> {
-    /// create a checked id string, the lexical rule is:
-    /// `^[a-zA-Z\x{80}-\x{ff}_][a-zA-Z\x{80}-\x{ff}\d_]*$`
-    pub fn id(data: &'a str) -> anyhow::Result<Self> {
-        static PATTERN: &str = r#"^[a-zA-Z\x{80}-\x{ff}_][a-zA-Z\x{80}-\x{ff}\d_]*$"#;
-        let re = regex::Regex::new(PATTERN).unwrap();
-        if re.is_match(data) {
-            Ok(Identity::String(data))
-        } else {
-            Err(anyhow::anyhow!("invalid identity format"))
+    fn visit_subgraph(&mut self, sub: &mut SubGraph<'a>) {
+        match sub {
+            SubGraph::SubGraph { stmts, .. } | SubGraph::Cluster(stmts) => self.visit(stmts),
         }
     }
-    /// create a quoted string
-    pub fn quoted(data: &'a str) -> Self {
-        Identity::Quoted(data)
+
+    fn visit(&mut self, list: &mut StmtList<'a>) {
+        for stmt in list.0.iter_mut() {
+            match stmt {
+                Stmt::Node { id, attr, .. } => {
+                    self.declared.insert(id.clone());
+                    let value = self.node_id(id);
+                    push_attr(attr, Identity::String("id"), value);
+                }
+                Stmt::Edge(edge) => {
+                    let mut name = self.endpoint(&mut edge.node);
+                    for body in edge.body.iter_mut() {
+                        name.push('-');
+                        name.push_str(&self.endpoint(&mut body.node));
+                    }
+                    let value = self.unique(&name);
+                    push_attr(&mut edge.attr, Identity::String("id"), Identity::OwnedQuoted(value));
+                }
+                Stmt::SubGraph(sub) => self.visit_subgraph(sub),
+                Stmt::Attr(..) | Stmt::Equation(..) | Stmt::Comment(..) | Stmt::LineComment(..) => {}
+            }
+        }
     }
 }
 
-impl<'a> Port<'a> {
-    /// corresponds to `:id`
-    pub fn id(i: Identity<'a>) -> Self {
-        Port::ID(i, None)
+/// Append a pair to the last bracket of an optional attribute list, creating the list if needed.
+fn push_attr<'a>(attr: &mut Option<AttrList<'a>>, key: Identity<'a>, value: Identity<'a>) {
+    let list = attr.take().unwrap_or_default();
+    attr.replace(list.add(key, value));
+}
+
+impl<'a> EdgeBuilder<'a> {
+    fn push(mut self, node: EdgeNode<'a>, op: EdgeOp) -> Self {
+        self.body.get_or_insert_with(Vec::new).push(EdgeBody { node, op });
+        self
     }
-    /// corresponds to `:id:<direction>`
-    pub fn id_compass(i: Identity<'a>, c: Compass) -> Self {
-        Port::ID(i, Some(c))
+    /// Connect to a new node with line
+    #[must_use]
+    pub fn line_to_node(self, id: Identity<'a>, port: Option<Port<'a>>) -> Self {
+        self.push(EdgeNode::Node { id, port }, EdgeOp::Line)
     }
-    /// corresponds to `:<direction>`
-    pub fn compass(c: Compass) -> Self {
-        Port::Compass(c)
+    /// Connect to a new subgraph with line
+    #[must_use]
+    pub fn line_to_subgraph(self, sub: SubGraph<'a>) -> Self {
+        self.push(EdgeNode::SubGraph(sub), EdgeOp::Line)
+    }
+    /// Connect to a new node with arrow
+    #[must_use]
+    pub fn arrow_to_node(self, id: Identity<'a>, port: Option<Port<'a>>) -> Self {
+        self.push(EdgeNode::Node { id, port }, EdgeOp::Arrow)
+    }
+    /// Connect to a new subgraph with arrow
+    #[must_use]
+    pub fn arrow_to_subgraph(self, sub: SubGraph<'a>) -> Self {
+        self.push(EdgeNode::SubGraph(sub), EdgeOp::Arrow)
     }
 }
 
 impl<'a> std::fmt::Display for Graph<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        if self.strict {
+        match &self.header {
+            Some(header) if f.alternate() => write_comment(f, header, true).and(writeln!(f)),
+            Some(header) => write_comment(f, header, false),
+            None => Ok(()),
+        }.and(if self.strict {
             write!(f, "strict ")
         } else {
             Ok(())
@@ -338,26 +1647,152 @@ impl<'a> std::fmt::Display for Graph<'a> {
             }
         ).and(
             match &self.id {
-                Some(id) => if f.alternate() { write!(f, "{} ", id) } else { write!(f, "{}", id) },
+                Some(id) => write!(f, "{} ", id),
                 _ => Ok(())
             }
         ).and(
             if f.alternate() {
-                let padding = f.width().unwrap_or(0) + 4;
-                let buffer = format!("{:width$}", self.stmts, width = padding);
-                write!(f, "{{\n").and(
-                    buffer.trim().split("\n").fold(Ok(()), |x, y| {
-                        x.and(
-                            write!(f, "{}", " ".repeat(padding))
-                        ).and(
-                            write!(f, "{}\n", y)
-                        )
-                    }).and(write!(f, "}}"))
-                )
+                write_block(f, &self.stmts, f.width().unwrap_or(4))
             } else {
                 write!(f, "{{{}}}", self.stmts)
             }
-        )
+        ))
+    }
+}
+
+impl<'a> Identity<'a> {
+    /// Copy borrowed strings into owned storage: `String` becomes `Owned` and `Quoted` becomes
+    /// `OwnedQuoted`. Arrow names are turned into an `Owned` string; all variants render as before.
+    pub fn into_owned(self) -> Identity<'static> {
+        use Identity::*;
+        match self {
+            String(s) => Owned(s.to_string()),
+            Quoted(s) => OwnedQuoted(s.to_string()),
+            #[cfg(feature = "attributes")]
+            ArrowName(_) => Owned(self.to_string()),
+            Usize(x) => Usize(x),
+            ISize(x) => ISize(x),
+            I8(x) => I8(x),
+            U8(x) => U8(x),
+            I16(x) => I16(x),
+            U16(x) => U16(x),
+            I32(x) => I32(x),
+            U32(x) => U32(x),
+            Bool(x) => Bool(x),
+            I64(x) => I64(x),
+            U64(x) => U64(x),
+            I128(x) => I128(x),
+            U128(x) => U128(x),
+            Float(x) => Float(x),
+            Double(x) => Double(x),
+            Owned(s) => Owned(s),
+            OwnedQuoted(s) => OwnedQuoted(s),
+            EscString(s) => EscString(s),
+            #[cfg(feature = "attributes")]
+            RGBA(r, g, b, a) => RGBA(r, g, b, a),
+            #[cfg(feature = "attributes")]
+            HSV(h, s, v) => HSV(h, s, v),
+            #[cfg(feature = "attributes")]
+            Point2D(x, y, fixed) => Point2D(x, y, fixed),
+            #[cfg(feature = "attributes")]
+            Point3D(x, y, z, fixed) => Point3D(x, y, z, fixed),
+        }
+    }
+}
+
+impl<'a> AttrList<'a> {
+    /// Copy all borrowed strings into owned storage, see `Identity::into_owned`.
+    pub fn into_owned(self) -> AttrList<'static> {
+        AttrList(self.0.into_iter()
+            .map(|list| list.into_iter().map(|(k, v)| (k.into_owned(), v.into_owned())).collect())
+            .collect())
+    }
+}
+
+impl<'a> StmtList<'a> {
+    /// Copy all borrowed strings into owned storage, see `Identity::into_owned`.
+    pub fn into_owned(self) -> StmtList<'static> {
+        self.0.into_iter().map(Stmt::into_owned).collect()
+    }
+}
+
+impl<'a> Stmt<'a> {
+    /// Copy all borrowed strings into owned storage, see `Identity::into_owned`.
+    pub fn into_owned(self) -> Stmt<'static> {
+        match self {
+            Stmt::Edge(edge) => Stmt::Edge(edge.into_owned()),
+            Stmt::Node { id, port, attr } => Stmt::Node {
+                id: id.into_owned(),
+                port: port.map(Port::into_owned),
+                attr: attr.map(AttrList::into_owned),
+            },
+            Stmt::Attr(attr_type, list) => Stmt::Attr(attr_type, list.into_owned()),
+            Stmt::Equation(k, v) => Stmt::Equation(k.into_owned(), v.into_owned()),
+            Stmt::Comment(text) => Stmt::Comment(Cow::Owned(text.into_owned())),
+            Stmt::LineComment(text) => Stmt::LineComment(Cow::Owned(text.into_owned())),
+            Stmt::SubGraph(sub) => Stmt::SubGraph(sub.into_owned()),
+        }
+    }
+}
+
+impl<'a> Edge<'a> {
+    /// Copy all borrowed strings into owned storage, see `Identity::into_owned`.
+    pub fn into_owned(self) -> Edge<'static> {
+        Edge {
+            node: self.node.into_owned(),
+            body: self.body.into_iter()
+                .map(|body| EdgeBody { node: body.node.into_owned(), op: body.op })
+                .collect(),
+            attr: self.attr.map(AttrList::into_owned),
+        }
+    }
+}
+
+impl<'a> EdgeNode<'a> {
+    /// Copy all borrowed strings into owned storage, see `Identity::into_owned`.
+    pub fn into_owned(self) -> EdgeNode<'static> {
+        match self {
+            EdgeNode::Node { id, port } => EdgeNode::Node { id: id.into_owned(), port: port.map(Port::into_owned) },
+            EdgeNode::SubGraph(sub) => EdgeNode::SubGraph(sub.into_owned()),
+        }
+    }
+}
+
+impl<'a> SubGraph<'a> {
+    /// Copy all borrowed strings into owned storage, see `Identity::into_owned`.
+    pub fn into_owned(self) -> SubGraph<'static> {
+        match self {
+            SubGraph::SubGraph { id, stmts } => SubGraph::SubGraph {
+                id: id.map(Identity::into_owned),
+                stmts: Box::new(stmts.into_owned()),
+            },
+            SubGraph::Cluster(stmts) => SubGraph::Cluster(Box::new(stmts.into_owned())),
+        }
+    }
+}
+
+impl<'a> Port<'a> {
+    /// Copy all borrowed strings into owned storage, see `Identity::into_owned`.
+    pub fn into_owned(self) -> Port<'static> {
+        match self {
+            Port::ID(id, compass) => Port::ID(id.into_owned(), compass),
+            Port::Compass(compass) => Port::Compass(compass),
+        }
+    }
+}
+
+impl<'a> Graph<'a> {
+    /// Copy all borrowed strings into owned storage, so the graph no longer borrows from
+    /// the strings it was built from and can be stored or returned freely.
+    /// The rendered output does not change, see `Identity::into_owned`.
+    pub fn into_owned(self) -> Graph<'static> {
+        Graph {
+            graph_type: self.graph_type,
+            strict: self.strict,
+            id: self.id.map(Identity::into_owned),
+            stmts: self.stmts.into_owned(),
+            header: self.header.map(|header| Cow::Owned(header.into_owned())),
+        }
     }
 }
 
@@ -382,7 +1817,9 @@ impl<'a> std::fmt::Display for Identity<'a> {
         use Identity::*;
         match self {
             #[cfg(feature = "attributes")]
-            RGBA(r, g, b, a) => write!(f, "\"#{:x}{:x}{:x}{:x}\"", r, g, b, a),
+            RGBA(r, g, b, 255) => write!(f, "\"#{:02x}{:02x}{:02x}\"", r, g, b),
+            #[cfg(feature = "attributes")]
+            RGBA(r, g, b, a) => write!(f, "\"#{:02x}{:02x}{:02x}{:02x}\"", r, g, b, a),
             #[cfg(feature = "attributes")]
             HSV(h, s, v) => write!(f, "\"{},+{},+{}\"", h, s, v),
             #[cfg(feature = "attributes")]
@@ -402,10 +1839,15 @@ impl<'a> std::fmt::Display for Identity<'a> {
                         } else { Ok(()) }
                     ),
             String(id) => write!(f, "{}", id),
+            Owned(id) => write!(f, "{}", id),
             Usize(id) => write!(f, "{}", id),
+            Float(id) if !id.is_finite() => write!(f, "\"{}\"", id),
+            Double(id) if !id.is_finite() => write!(f, "\"{}\"", id),
             Float(id) => write!(f, "{}", id),
             Double(id) => write!(f, "{}", id),
-            Quoted(id) => write!(f, "{:?}", id),
+            Quoted(id) => write_quoted(f, id),
+            OwnedQuoted(id) => write_quoted(f, id),
+            EscString(id) => write!(f, "\"{}\"", id),
             ISize(id) => write!(f, "{}", id),
             I8(id) => write!(f, "{}", id),
             U8(id) => write!(f, "{}", id),
@@ -420,14 +1862,13 @@ impl<'a> std::fmt::Display for Identity<'a> {
             Bool(flag) => write!(f, "{}", flag),
             #[cfg(feature = "attributes")]
             ArrowName(names) => {
-                names.iter().fold(Ok(()), |acc, x| {
-                    acc.and(
-                        match x {
-                            None => Ok(()),
-                            Some(e) => {
-                                write!(f, "{}", e)
-                            }
-                        })
+                names.iter().try_for_each(|x| {
+                    match x {
+                        None => Ok(()),
+                        Some(e) => {
+                            write!(f, "{}", e)
+                        }
+                    }
                 })
             }
         }
@@ -450,22 +1891,55 @@ impl<'a> std::fmt::Display for Port<'a> {
 impl<'a> std::fmt::Display for AttrList<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         self.0.iter()
-            .fold(Ok(()), |acc, list| {
-                acc.and(write!(f, "["))
-                    .and(list
-                        .iter()
-                        .fold(Ok(()), |acc, (x, y)| {
-                            if f.width().is_some() {
-                                acc.and(write!(f, "{}={}; ", x, y))
-                            } else {
-                                acc.and(write!(f, "{}={};", x, y))
-                            }
-                        }))
+            .filter(|list| !list.is_empty())
+            .try_for_each(|list| {
+                write!(f, "[")
+                    .and(write_pairs(f, list.iter()))
                     .and(write!(f, "]"))
             })
     }
 }
 
+/// Write `key=value` pairs separated by commas, followed by a space in pretty mode.
+fn write_pairs<'l, 'a: 'l, I>(f: &mut Formatter<'_>, pairs: I) -> Result
+    where I: Iterator<Item=&'l (Identity<'a>, Identity<'a>)> {
+    let separator = if f.width().is_some() { ", " } else { "," };
+    pairs.enumerate().try_for_each(|(i, (x, y))| {
+        if i > 0 {
+            write!(f, "{}", separator)
+        } else {
+            Ok(())
+        }.and(write!(f, "{}={}", x, y))
+    })
+}
+
+impl<'a> AttrList<'a> {
+    /// Write the pairs of all brackets as a single comma separated list without the surrounding
+    /// `[` `]`, e.g. to compose attributes into another format. Like `Display`, pretty mode
+    /// (a width is given, as in `{:#4}`) separates the pairs with `, `.
+    pub fn fmt_inner(&self, f: &mut Formatter<'_>) -> Result {
+        write_pairs(f, self.0.iter().flatten())
+    }
+    /// The pairs rendered by `fmt_inner`
+    /// ```
+    /// use tabbycat::{AttrList, Identity};
+    /// let list = AttrList::new()
+    ///     .add(Identity::id("color").unwrap(), Identity::id("red").unwrap())
+    ///     .new_bracket()
+    ///     .add(Identity::id("label").unwrap(), Identity::quoted("a b"));
+    /// assert_eq!(r#"color=red,label="a b""#, list.to_inner_string());
+    /// ```
+    pub fn to_inner_string(&self) -> String {
+        struct Inner<'l, 'a>(&'l AttrList<'a>);
+        impl std::fmt::Display for Inner<'_, '_> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+                self.0.fmt_inner(f)
+            }
+        }
+        Inner(self).to_string()
+    }
+}
+
 impl<'a> std::fmt::Display for Stmt<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         use Stmt as S;
@@ -485,14 +1959,20 @@ impl<'a> std::fmt::Display for Stmt<'a> {
                         Some(p) => write!(f, "{}", p)
                     })
                     .and(match attr {
-                        None => Ok(()),
-                        Some(a) => if let Some(w) = f.width() {
+                        Some(a) if !a.is_empty() => if let Some(w) = f.width() {
                             write!(f, " {:width$}", a, width = w)
                         } else {
                             write!(f, "{}", a)
-                        }
+                        },
+                        _ => Ok(()),
                     })
             }
+            // the grammar requires a bracket after `node`, `edge` and `graph`
+            S::Attr(t, list) if list.is_empty() => match t {
+                AttrType::Node => write!(f, "node []"),
+                AttrType::Graph => write!(f, "graph []"),
+                AttrType::Edge => write!(f, "edge []")
+            },
             S::Attr(t, list) => {
                 if let Some(w) = f.width() {
                     match t {
@@ -515,6 +1995,8 @@ impl<'a> std::fmt::Display for Stmt<'a> {
                     write!(f, "{}", sub)
                 }
             }
+            S::Comment(text) => write_comment(f, text, false),
+            S::LineComment(text) => write_comment(f, text, f.width().is_some()),
         }
     }
 }
@@ -522,21 +2004,53 @@ impl<'a> std::fmt::Display for Stmt<'a> {
 impl<'a> std::fmt::Display for StmtList<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         if let Some(w) = f.width() {
+            let heads: Vec<_> = self.0.iter().map(|x| aligned_head(x, w)).collect();
+            let column = heads.iter().flatten().map(|(head, _)| head.chars().count()).max().unwrap_or(0);
             self.0
                 .iter()
-                .fold(Ok(()), |acc, x| {
-                    acc.and(write!(f, "{:width$};\n", x, width = w))
+                .zip(heads)
+                .try_for_each(|(x, head)| match (x, head) {
+                    (_, Some((head, list))) => writeln!(f, "{:column$} {:width$};", head, list, column = column, width = w),
+                    (Stmt::Comment(..), _) | (Stmt::LineComment(..), _) => writeln!(f, "{:width$}", x, width = w),
+                    _ => writeln!(f, "{:width$};", x, width = w),
                 })
         } else {
             self.0
                 .iter()
-                .fold(Ok(()), |acc, x| {
-                    acc.and(write!(f, "{};", x))
+                .try_for_each(|x| match x {
+                    Stmt::Comment(..) | Stmt::LineComment(..) => write!(f, "{}", x),
+                    _ => write!(f, "{};", x),
                 })
         }
     }
 }
 
+/// In pretty mode, a statement ending with attributes is split into the part before its attribute
+/// list and the list, so that the lists of a block can start in the same column. Edges with
+/// subgraph endpoints span several lines and are left as they are.
+fn aligned_head<'s, 'a>(stmt: &'s Stmt<'a>, width: usize) -> Option<(String, &'s AttrList<'a>)> {
+    match stmt {
+        Stmt::Node { id, port, attr: Some(list) } if !list.is_empty() => Some((match port {
+            Some(port) => format!("{}{}", id, port),
+            None => id.to_string(),
+        }, list)),
+        Stmt::Edge(Edge { node, body, attr: Some(list) }) if !list.is_empty() => {
+            let ends = std::iter::once(node).chain(body.iter().map(|body| &body.node));
+            if ends.clone().any(|node| matches!(node, EdgeNode::SubGraph(_))) {
+                return None;
+            }
+            let edge = Edge { node: node.clone(), body: body.clone(), attr: None };
+            Some((format!("{:width$}", edge, width = width), list))
+        }
+        Stmt::Attr(t, list) if !list.is_empty() => Some((match t {
+            AttrType::Node => "node",
+            AttrType::Graph => "graph",
+            AttrType::Edge => "edge",
+        }.to_string(), list)),
+        _ => None,
+    }
+}
+
 impl<'a> std::fmt::Display for SubGraph<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         match self {
@@ -551,16 +2065,7 @@ impl<'a> std::fmt::Display for SubGraph<'a> {
                         }
                     ).and(
                     if let Some(w) = f.width() {
-                        let buffer = format!("{:width$}", stmts, width = w);
-                        write!(f, "{{\n").and(
-                            buffer.trim().split("\n").fold(Ok(()), |x, y| {
-                                x.and(
-                                    write!(f, "{}", " ".repeat(w))
-                                ).and(
-                                    write!(f, "{}\n", y)
-                                )
-                            }).and(write!(f, "}}"))
-                        )
+                        write_block(f, stmts, w)
                     } else {
                         write!(f, "{{{}}}", stmts)
                     }
@@ -568,16 +2073,7 @@ impl<'a> std::fmt::Display for SubGraph<'a> {
             }
             SubGraph::Cluster(stmts) => {
                 if let Some(w) = f.width() {
-                    let buffer = format!("{:width$}", stmts, width = w);
-                    write!(f, "{{\n").and(
-                        buffer.trim().split("\n").fold(Ok(()), |x, y| {
-                            x.and(
-                                write!(f, "{}", " ".repeat(w))
-                            ).and(
-                                write!(f, "{}\n", y)
-                            )
-                        }).and(write!(f, "}}"))
-                    )
+                    write_block(f, stmts, w)
                 } else {
                     write!(f, "{{{}}}", stmts)
                 }
@@ -628,15 +2124,15 @@ impl<'a> std::fmt::Display for Edge<'a> {
         } else {
             write!(f, "{}", self.node)
         }
-            .and(self.body.iter().fold(Ok(()), |acc, x| {
-                acc.and(if let Some(w) = f.width() {
+            .and(self.body.iter().try_for_each(|x| {
+                if let Some(w) = f.width() {
                     write!(f, "{:width$}", x, width = w)
                 } else {
                     write!(f, "{}", x)
-                })
+                }
             }))
             .and(match &self.attr {
-                Some(x) => if let Some(w) = f.width() {
+                Some(x) if !x.is_empty() => if let Some(w) = f.width() {
                     write!(f, " {:width$}", x, width = w)
                 } else {
                     write!(f, "{}", x)
@@ -651,16 +2147,55 @@ impl<'a> AttrList<'a> {
     pub fn new() -> Self {
         AttrList(Vec::new())
     }
+    /// Sort the pairs of every bracket by their key. The sort is stable, so repeated keys keep
+    /// their order, and pairs never move to another bracket.
+    #[must_use]
+    pub fn sorted(mut self) -> Self {
+        self.sort_in_place();
+        self
+    }
+    /// Move all pairs into a single bracket, e.g. `[a=b][c=d]` becomes `[a=b,c=d]`. Pairs keep
+    /// their order, so repeated keys still resolve to the same value.
+    #[must_use]
+    pub fn flattened(mut self) -> Self {
+        self.flatten_in_place();
+        self
+    }
+    fn flatten_in_place(&mut self) {
+        let pairs: Vec<_> = std::mem::take(&mut self.0).into_iter().flatten().collect();
+        if !pairs.is_empty() {
+            self.0.push(pairs);
+        }
+    }
+    fn sort_in_place(&mut self) {
+        for list in self.0.iter_mut() {
+            list.sort_by_cached_key(|(k, _)| k.to_string());
+        }
+    }
+    /// Whether the list holds no attributes. Empty brackets do not count, they are left out of the output.
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(Vec::is_empty)
+    }
+    /// The number of attributes in all brackets
+    pub fn len(&self) -> usize {
+        self.0.iter().map(Vec::len).sum()
+    }
+    /// The number of brackets that hold attributes, i.e. of `[...]` groups in the output
+    pub fn group_count(&self) -> usize {
+        self.0.iter().filter(|list| !list.is_empty()).count()
+    }
     /// The dot language support multiple attribute lists with a syntax like:
     /// ```dot
-    /// A->B [color=red;][label="abc";];
+    /// A->B [color=red][label="abc"];
     /// ```
     /// This function is used to open a new bracket and later attributes will be added to the new one.
+    #[must_use]
     pub fn new_bracket(mut self) -> Self {
         self.0.push(Vec::new());
         self
     }
     /// Append a list of new attributes to the current bracket
+    #[must_use]
     pub fn extend<I: IntoIterator<Item=AttrPair<'a>>>(mut self, iter: I) -> Self {
         if self.0.is_empty() {
             self = self.new_bracket();
@@ -669,11 +2204,13 @@ impl<'a> AttrList<'a> {
         self
     }
     /// Add a new attribute list
+    #[must_use]
     pub fn extend_list<I: IntoIterator<Item=Vec<AttrPair<'a>>>>(mut self, iter: I) -> Self {
         self.0.extend(iter);
         self
     }
     /// Add a new attribute
+    #[must_use]
     pub fn add(mut self, key: Identity<'a>, value: Identity<'a>) -> Self {
         if self.0.is_empty() {
             self = self.new_bracket();
@@ -682,9 +2219,49 @@ impl<'a> AttrList<'a> {
         self
     }
     /// Add a new attribute (in pair)
+    #[must_use]
     pub fn add_pair(self, pair: AttrPair<'a>) -> Self {
         self.add(pair.0, pair.1)
     }
+    /// Build an attribute list from plain key/value strings, e.g. read from a configuration file.
+    /// Values that are neither ids nor numerals are quoted. The strings are copied, so any pair of
+    /// `&str`, `String` or references to them works.
+    /// ```
+    /// use tabbycat::AttrList;
+    /// let list = AttrList::from_string_pairs(vec![("shape", "box"), ("label", "a b")]);
+    /// assert_eq!(r#"[shape=box,label="a b"]"#, list.to_string());
+    /// ```
+    pub fn from_string_pairs<I, K, V>(pairs: I) -> Self
+        where I: IntoIterator<Item=(K, V)>,
+              K: AsRef<str>,
+              V: AsRef<str> {
+        pairs.into_iter().fold(AttrList::new(), |list, (k, v)| list.add(
+            Identity::auto(k.as_ref()).into_owned(),
+            Identity::auto(v.as_ref()).into_owned()))
+    }
+    /// All pairs of all brackets as plain strings; quoted values are returned without their quotes.
+    pub fn to_string_pairs(&self) -> Vec<(String, String)> {
+        self.0.iter()
+            .flatten()
+            .map(|(k, v)| (k.unquoted(), v.unquoted()))
+            .collect()
+    }
+    /// Merge another attribute list into this one. A key that is already present (in any bracket)
+    /// takes the value from `other`, new keys are appended to the current bracket.
+    #[must_use]
+    pub fn merge(mut self, other: AttrList<'a>) -> Self {
+        for (key, value) in other.0.into_iter().flatten() {
+            let existing = self.0.iter_mut()
+                .flat_map(|list| list.iter_mut())
+                .filter(|(k, _)| *k == key)
+                .last();
+            match existing {
+                Some((_, v)) => *v = value,
+                None => self = self.add(key, value),
+            }
+        }
+        self
+    }
 }
 
 impl<'a> StmtList<'a> {
@@ -693,16 +2270,44 @@ impl<'a> StmtList<'a> {
         StmtList(Vec::new())
     }
     /// Add a statement
+    #[allow(clippy::should_implement_trait)]
+    #[must_use]
     pub fn add(mut self, stmt: Stmt<'a>) -> Self {
         self.0.push(stmt);
         self
     }
+    /// The statement at `index`
+    pub fn get(&self, index: usize) -> Option<&Stmt<'a>> {
+        self.0.get(index)
+    }
+    /// The statement at `index`, to edit it in place
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Stmt<'a>> {
+        self.0.get_mut(index)
+    }
+    /// Iterate over the statements, not including those inside subgraphs
+    pub fn iter(&self) -> std::slice::Iter<'_, Stmt<'a>> {
+        self.0.iter()
+    }
+    /// Iterate over the statements to edit them in place, not including those inside subgraphs
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Stmt<'a>> {
+        self.0.iter_mut()
+    }
+    /// The number of statements, not counting those inside subgraphs
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    /// Whether the list has no statements
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
     /// Append a list a statements
+    #[must_use]
     pub fn extend<I: IntoIterator<Item=Stmt<'a>>>(mut self, iter: I) -> Self {
         self.0.extend(iter);
         self
     }
     /// Add a node statement
+    #[must_use]
     pub fn add_node(mut self, id: Identity<'a>, port: Option<Port<'a>>, attr: Option<AttrList<'a>>) -> Self {
         self.0.push(Stmt::Node {
             id,
@@ -712,6 +2317,7 @@ impl<'a> StmtList<'a> {
         self
     }
     /// Add a global attribute
+    #[must_use]
     pub fn add_attr(mut self, attr_type: AttrType, attr_list: AttrList<'a>) -> Self {
         self.0.push(Stmt::Attr(
             attr_type,
@@ -719,14 +2325,73 @@ impl<'a> StmtList<'a> {
         ));
         self
     }
+    /// Add default attributes for the nodes that follow, written as `node [...]`
+    #[must_use]
+    pub fn with_node_defaults(self, attr_list: AttrList<'a>) -> Self {
+        self.add_attr(AttrType::Node, attr_list)
+    }
+    /// Add default attributes for the edges that follow, written as `edge [...]`
+    #[must_use]
+    pub fn with_edge_defaults(self, attr_list: AttrList<'a>) -> Self {
+        self.add_attr(AttrType::Edge, attr_list)
+    }
+    /// Add attributes of the (sub)graph, written as `graph [...]`
+    #[must_use]
+    pub fn with_graph_defaults(self, attr_list: AttrList<'a>) -> Self {
+        self.add_attr(AttrType::Graph, attr_list)
+    }
     /// Add an edge statement
+    #[must_use]
     pub fn add_edge(mut self, edge: Edge<'a>) -> Self {
         self.0.push(Stmt::Edge(
             edge
         ));
         self
     }
+    /// Add an edge statement connecting the node `a` to the node `b` with `op`, without ports or attributes
+    #[must_use]
+    pub fn add_edge_between(self, a: Identity<'a>, b: Identity<'a>, op: EdgeOp) -> Self {
+        self.add_edge(Edge {
+            node: EdgeNode::Node { id: a, port: None },
+            body: vec![EdgeBody { node: EdgeNode::Node { id: b, port: None }, op }],
+            attr: None,
+        })
+    }
+    /// Like `add_edge_between`, with a port on each end, e.g. to connect record fields
+    /// ```
+    /// use tabbycat::{EdgeOp, Identity, Port, StmtList};
+    /// let stmts = StmtList::new().add_edge_between_ports(
+    ///     (Identity::id("a").unwrap(), Some(Port::field("p1"))),
+    ///     (Identity::id("b").unwrap(), Some(Port::field("p2"))),
+    ///     EdgeOp::Arrow);
+    /// assert_eq!("a:p1->b:p2;", stmts.to_string());
+    /// ```
+    #[must_use]
+    pub fn add_edge_between_ports(self, a: (Identity<'a>, Option<Port<'a>>), b: (Identity<'a>, Option<Port<'a>>), op: EdgeOp) -> Self {
+        self.add_edge(Edge {
+            node: EdgeNode::Node { id: a.0, port: a.1 },
+            body: vec![EdgeBody { node: EdgeNode::Node { id: b.0, port: b.1 }, op }],
+            attr: None,
+        })
+    }
+    /// Add one edge statement from each source to each of its targets, in order, like
+    /// `add_edge_between`. Sources without targets add nothing.
+    /// ```
+    /// use tabbycat::{EdgeOp, Identity, StmtList};
+    /// let id = |name| Identity::id(name).unwrap();
+    /// let stmts = StmtList::new().add_adjacency(vec![(id("a"), vec![id("b"), id("c")]), (id("b"), vec![id("c")])], EdgeOp::Arrow);
+    /// assert_eq!("a->b;a->c;b->c;", stmts.to_string());
+    /// ```
+    #[must_use]
+    pub fn add_adjacency<I, T>(self, adjacency: I, op: EdgeOp) -> Self
+        where I: IntoIterator<Item=(Identity<'a>, T)>,
+              T: IntoIterator<Item=Identity<'a>> {
+        adjacency.into_iter().fold(self, |list, (source, targets)| {
+            targets.into_iter().fold(list, |list, target| list.add_edge_between(source.clone(), target, op))
+        })
+    }
     /// Add a subgraph statement
+    #[must_use]
     pub fn add_subgraph(mut self, sub: SubGraph<'a>) -> Self {
         self.0.push(Stmt::SubGraph(
             sub
@@ -734,12 +2399,64 @@ impl<'a> StmtList<'a> {
         self
     }
     /// Add an equation
+    #[must_use]
     pub fn add_equation(mut self, a: Identity<'a>, b: Identity<'a>) -> Self {
         self.0.push(Stmt::Equation(
             a, b,
         ));
         self
     }
+    /// Add a plain node statement (without port or attributes) for each id, in order
+    #[must_use]
+    pub fn add_nodes<I: IntoIterator<Item=Identity<'a>>>(mut self, ids: I) -> Self {
+        self.0.extend(ids.into_iter().map(|id| Stmt::Node { id, port: None, attr: None }));
+        self
+    }
+    /// Add a `/* ... */` comment
+    #[must_use]
+    pub fn add_comment<S: Into<Cow<'a, str>>>(mut self, text: S) -> Self {
+        self.0.push(Stmt::Comment(text.into()));
+        self
+    }
+    /// Append all statements of `other` after the statements of this list
+    #[must_use]
+    pub fn merge(mut self, other: StmtList<'a>) -> Self {
+        self.0.extend(other.0);
+        self
+    }
+    /// Add a `// ...` comment, see `Stmt::LineComment`
+    #[must_use]
+    pub fn add_line_comment<S: Into<Cow<'a, str>>>(mut self, text: S) -> Self {
+        self.0.push(Stmt::LineComment(text.into()));
+        self
+    }
+    /// Append an attribute to the first node statement for `id`, looking into subgraphs and clusters
+    /// too; an attribute list is created when the node has none. When there is no node statement for
+    /// `id` yet, e.g. because it only appears in edges, `id [pair]` is added to this list.
+    /// ```
+    /// use tabbycat::{AttrList, Identity, StmtList};
+    /// let stmts = StmtList::new()
+    ///     .add_node(Identity::id("a").unwrap(), None, Some(AttrList::new().add(Identity::id("shape").unwrap(), Identity::id("box").unwrap())))
+    ///     .add_node_attribute(Identity::id("a").unwrap(), (Identity::id("color").unwrap(), Identity::id("red").unwrap()));
+    /// assert_eq!("a[shape=box,color=red];", stmts.to_string());
+    /// ```
+    #[must_use]
+    pub fn add_node_attribute(mut self, id: Identity<'a>, pair: AttrPair<'a>) -> Self {
+        fn find<'a, 'b>(list: &'b mut StmtList<'a>, id: &Identity<'a>) -> Option<&'b mut Option<AttrList<'a>>> {
+            list.0.iter_mut().find_map(|stmt| match stmt {
+                Stmt::Node { id: node, attr, .. } if node == id => Some(attr),
+                Stmt::SubGraph(sub) => find(sub.stmts_mut(), id),
+                _ => None,
+            })
+        }
+        match find(&mut self, &id) {
+            Some(attr) => {
+                *attr = Some(attr.take().unwrap_or_default().add_pair(pair));
+                self
+            }
+            None => self.add_node(id, None, Some(AttrList::new().add_pair(pair))),
+        }
+    }
 }
 
 impl<'a> Edge<'a> {
@@ -762,8 +2479,32 @@ impl<'a> Edge<'a> {
             attr: None,
         }
     }
+    /// Build a chain of arrows through all the given nodes, e.g. `a->b->c` for a linear pipeline
+    /// ```
+    /// use tabbycat::{Edge, Identity};
+    /// let ids = ["b", "c", "d"].iter().map(|id| Identity::id(id).unwrap());
+    /// assert_eq!("a->b->c->d", Edge::arrow_path(Identity::id("a").unwrap(), ids).to_string());
+    /// ```
+    pub fn arrow_path<I: IntoIterator<Item=Identity<'a>>>(head: Identity<'a>, rest: I) -> Self {
+        rest.into_iter().fold(Edge::head_node(head, None), |edge, id| edge.arrow_to_node(id, None))
+    }
+    /// Build a chain of lines through all the given nodes, see `Edge::arrow_path`
+    pub fn line_path<I: IntoIterator<Item=Identity<'a>>>(head: Identity<'a>, rest: I) -> Self {
+        rest.into_iter().fold(Edge::head_node(head, None), |edge, id| edge.line_to_node(id, None))
+    }
+    /// Like `Edge::arrow_path`, with an optional port on every node
+    pub fn arrow_path_ports<I>(head: (Identity<'a>, Option<Port<'a>>), rest: I) -> Self
+        where I: IntoIterator<Item=(Identity<'a>, Option<Port<'a>>)> {
+        rest.into_iter().fold(Edge::head_node(head.0, head.1), |edge, (id, port)| edge.arrow_to_node(id, port))
+    }
+    /// Like `Edge::line_path`, with an optional port on every node
+    pub fn line_path_ports<I>(head: (Identity<'a>, Option<Port<'a>>), rest: I) -> Self
+        where I: IntoIterator<Item=(Identity<'a>, Option<Port<'a>>)> {
+        rest.into_iter().fold(Edge::head_node(head.0, head.1), |edge, (id, port)| edge.line_to_node(id, port))
+    }
     /// Connect to a new node with line
     /// Notice that you should not use this in a directed graph. Unfortunately, this crate does not check this for you.
+    #[must_use]
     pub fn line_to_node(mut self, id: Identity<'a>, port: Option<Port<'a>>) -> Self {
         self.body.push(
             EdgeBody {
@@ -778,6 +2519,7 @@ impl<'a> Edge<'a> {
     }
     /// Connect to a new subgraph with line
     /// Notice that you should not use this in a directed graph. Unfortunately, this crate does not check this for you.
+    #[must_use]
     pub fn line_to_subgraph(mut self, sub: SubGraph<'a>) -> Self {
         self.body.push(
             EdgeBody {
@@ -789,6 +2531,7 @@ impl<'a> Edge<'a> {
     }
     /// Connect to a new node with arrow
     /// Notice that you should not use this in a undirected graph. Unfortunately, this crate does not check this for you.
+    #[must_use]
     pub fn arrow_to_node(mut self, id: Identity<'a>, port: Option<Port<'a>>) -> Self {
         self.body.push(
             EdgeBody {
@@ -803,6 +2546,7 @@ impl<'a> Edge<'a> {
     }
     /// Connect to a new subgraph with arrow
     /// Notice that you should not use this in a undirected graph. Unfortunately, this crate does not check this for you.
+    #[must_use]
     pub fn arrow_to_subgraph(mut self, sub: SubGraph<'a>) -> Self {
         self.body.push(
             EdgeBody {
@@ -813,30 +2557,39 @@ impl<'a> Edge<'a> {
         self
     }
     /// Add an attribute list to the edge
+    #[must_use]
     pub fn add_attrlist(mut self, list: AttrList<'a>) -> Self {
-        if self.attr.is_none() {
-            self.attr.replace(list);
-        } else {
-            self.attr.as_mut().unwrap().0.extend(list.0);
-            self.attr.as_mut().unwrap().0.push(Vec::new());
+        match &mut self.attr {
+            None => {
+                self.attr.replace(list);
+            }
+            Some(attr) => {
+                attr.0.extend(list.0);
+                attr.0.push(Vec::new());
+            }
         }
         self
     }
     /// Add an attribute to the edge
+    #[must_use]
     pub fn add_attribute(mut self, key: Identity<'a>, value: Identity<'a>) -> Self {
-        if self.attr.is_none() {
-            self.attr.replace(AttrList(vec![vec![(key, value)]]));
-        } else {
-            let vec = &mut self.attr.as_mut().unwrap().0;
-            if vec.is_empty() {
-                vec.push(vec![(key, value)]);
-            } else {
-                vec.last_mut().unwrap().push((key, value));
+        match &mut self.attr {
+            None => {
+                self.attr.replace(AttrList(vec![vec![(key, value)]]));
+            }
+            Some(attr) => {
+                let vec = &mut attr.0;
+                if vec.is_empty() {
+                    vec.push(vec![(key, value)]);
+                } else {
+                    vec.last_mut().unwrap().push((key, value));
+                }
             }
         }
         self
     }
     /// Add an attribute to the edge (in pair)
+    #[must_use]
     pub fn add_attrpair(self, pair: AttrPair<'a>) -> Self {
         self.add_attribute(pair.0, pair.1)
     }
@@ -0,0 +1,277 @@
+//! Export graphs as [GraphML](http://graphml.graphdrawing.org/).
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::graph::endpoint_ids;
+use crate::{AttrList, AttrType, EdgeNode, Graph, GraphType, Identity, Stmt, StmtList, SubGraph};
+
+/// Convert a graph into a GraphML document.
+///
+/// - node statements and nodes only referenced by edges become `<node>` elements, declared in the
+///   (sub)graph where they first appear; the attributes of repeated node statements are merged,
+/// - edge chains are split into one `<edge>` per hop, and a subgraph endpoint connects every node in it,
+/// - subgraphs become nested `<graph>` elements wrapped in a `<node>`, as GraphML requires; the node
+///   takes the subgraph id and the nested graph the same id followed by `:`. Anonymous subgraphs are
+///   named `_subgraph1`, `_subgraph2`, ..., and a subgraph id that a node or an earlier subgraph
+///   already uses gets a suffix `_1`, `_2`, ..., so every id stays unique,
+/// - graph attributes and equations become `<data>` of the enclosing `<graph>`,
+/// - `edgedefault` follows the `GraphType`.
+///
+/// Every attribute name is declared as a string `<key>` for all elements. Ports, comments and
+/// default `node`/`edge` attributes have no GraphML counterpart and are left out.
+/// ```
+/// use tabbycat::{to_graphml, EdgeOp, GraphBuilder, GraphType, Identity, StmtList};
+/// let graph = GraphBuilder::default()
+///     .graph_type(GraphType::DiGraph)
+///     .strict(false)
+///     .stmts(StmtList::new()
+///         .add_edge_between(Identity::id("a").unwrap(), Identity::id("b").unwrap(), EdgeOp::Arrow))
+///     .build()
+///     .unwrap();
+/// assert!(to_graphml(&graph).contains(r#"<edge source="a" target="b"/>"#));
+/// ```
+pub fn to_graphml(graph: &Graph) -> String {
+    let mut exporter = Exporter::default();
+    exporter.take_ids(&graph.stmts);
+    let id = graph.id.as_ref().map_or_else(|| "G".to_string(), Identity::unquoted);
+    let root = exporter.scope(id, &graph.stmts);
+    let keys: HashMap<&str, String> = exporter.keys.iter()
+        .enumerate()
+        .map(|(i, name)| (name.as_str(), format!("d{}", i)))
+        .collect();
+    let edge_default = match graph.graph_type {
+        GraphType::Graph => "undirected",
+        GraphType::DiGraph => "directed",
+    };
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    for name in exporter.keys.iter() {
+        out.push_str(&format!("  <key id=\"{}\" for=\"all\" attr.name=\"{}\" attr.type=\"string\"/>\n",
+                              keys[name.as_str()], escape(name)));
+    }
+    let writer = Writer { keys: &keys, node_attrs: &exporter.node_attrs, edge_default };
+    writer.scope(&root, &root.id, 1, &mut out);
+    out.push_str("</graphml>\n");
+    out
+}
+
+type Pairs = Vec<(String, String)>;
+
+struct Scope {
+    id: String,
+    data: Pairs,
+    items: Vec<Item>,
+}
+
+enum Item {
+    Node(String),
+    Graph(Scope),
+    Edge { source: String, target: String, data: Pairs },
+}
+
+#[derive(Default)]
+struct Exporter {
+    declared: HashSet<String>,
+    /// The node ids of the graph and the ids given to subgraphs so far, which subgraphs must not take
+    taken: HashSet<String>,
+    node_attrs: HashMap<String, Pairs>,
+    keys: BTreeSet<String>,
+    anonymous: usize,
+}
+
+impl Exporter {
+    /// Collect every node id in `list`, before any subgraph is named.
+    fn take_ids(&mut self, list: &StmtList) {
+        for stmt in list.0.iter() {
+            match stmt {
+                Stmt::Node { id, .. } => {
+                    self.taken.insert(id.unquoted());
+                }
+                Stmt::Edge(edge) => {
+                    for node in std::iter::once(&edge.node).chain(edge.body.iter().map(|body| &body.node)) {
+                        match node {
+                            EdgeNode::Node { id, .. } => {
+                                self.taken.insert(id.unquoted());
+                            }
+                            EdgeNode::SubGraph(sub) => self.take_ids(sub.stmts()),
+                        }
+                    }
+                }
+                Stmt::SubGraph(sub) => self.take_ids(sub.stmts()),
+                Stmt::Attr(..) | Stmt::Equation(..) | Stmt::Comment(..) | Stmt::LineComment(..) => {}
+            }
+        }
+    }
+
+    /// Take `id` for a subgraph, together with `id:` for its nested graph, if both are free.
+    fn claim(taken: &mut HashSet<String>, id: &str) -> bool {
+        let nested = format!("{}:", id);
+        if taken.contains(id) || taken.contains(&nested) {
+            return false;
+        }
+        taken.insert(id.to_string());
+        taken.insert(nested);
+        true
+    }
+
+    /// The next `<prefix><n>` after `counter` that `claim` accepts
+    fn fresh(taken: &mut HashSet<String>, prefix: &str, counter: &mut usize) -> String {
+        loop {
+            *counter += 1;
+            let id = format!("{}{}", prefix, counter);
+            if Self::claim(taken, &id) {
+                return id;
+            }
+        }
+    }
+
+    fn scope(&mut self, id: String, list: &StmtList) -> Scope {
+        let mut scope = Scope { id, data: Vec::new(), items: Vec::new() };
+        for stmt in list.0.iter() {
+            match stmt {
+                Stmt::Node { id, attr, .. } => {
+                    self.declare(id, &mut scope);
+                    let pairs = self.pairs(attr.as_ref());
+                    let merged = self.node_attrs.entry(id.unquoted()).or_default();
+                    for (key, value) in pairs {
+                        match merged.iter_mut().find(|(k, _)| *k == key) {
+                            Some(pair) => pair.1 = value,
+                            None => merged.push((key, value)),
+                        }
+                    }
+                }
+                Stmt::Edge(edge) => {
+                    let data = self.pairs(edge.attr.as_ref());
+                    for node in std::iter::once(&edge.node).chain(edge.body.iter().map(|body| &body.node)) {
+                        match node {
+                            EdgeNode::Node { id, .. } => self.declare(id, &mut scope),
+                            EdgeNode::SubGraph(sub) => {
+                                let nested = self.subgraph(sub);
+                                scope.items.push(Item::Graph(nested));
+                            }
+                        }
+                    }
+                    let mut from = &edge.node;
+                    for body in edge.body.iter() {
+                        let (mut sources, mut targets) = (Vec::new(), Vec::new());
+                        endpoint_ids(from, &mut sources);
+                        endpoint_ids(&body.node, &mut targets);
+                        for source in sources.iter() {
+                            for target in targets.iter() {
+                                scope.items.push(Item::Edge {
+                                    source: source.unquoted(),
+                                    target: target.unquoted(),
+                                    data: data.clone(),
+                                });
+                            }
+                        }
+                        from = &body.node;
+                    }
+                }
+                Stmt::Attr(AttrType::Graph, list) => {
+                    let pairs = self.pairs(Some(list));
+                    scope.data.extend(pairs);
+                }
+                Stmt::Equation(key, value) => {
+                    let pairs = self.pairs(Some(&AttrList::new().add(key.clone(), value.clone())));
+                    scope.data.extend(pairs);
+                }
+                Stmt::SubGraph(sub) => {
+                    let nested = self.subgraph(sub);
+                    scope.items.push(Item::Graph(nested));
+                }
+                Stmt::Attr(..) | Stmt::Comment(..) | Stmt::LineComment(..) => {}
+            }
+        }
+        scope
+    }
+
+    fn subgraph(&mut self, sub: &SubGraph) -> Scope {
+        let id = match sub {
+            SubGraph::SubGraph { id: Some(id), .. } => {
+                let id = id.unquoted();
+                if Self::claim(&mut self.taken, &id) {
+                    id
+                } else {
+                    Self::fresh(&mut self.taken, &format!("{}_", id), &mut 0)
+                }
+            }
+            _ => Self::fresh(&mut self.taken, "_subgraph", &mut self.anonymous),
+        };
+        self.scope(id, sub.stmts())
+    }
+
+    fn declare(&mut self, id: &Identity, scope: &mut Scope) {
+        let id = id.unquoted();
+        if self.declared.insert(id.clone()) {
+            scope.items.push(Item::Node(id));
+        }
+    }
+
+    fn pairs(&mut self, attr: Option<&AttrList>) -> Pairs {
+        let pairs = attr.map(AttrList::to_string_pairs).unwrap_or_default();
+        self.keys.extend(pairs.iter().map(|(key, _)| key.clone()));
+        pairs
+    }
+}
+
+struct Writer<'w> {
+    keys: &'w HashMap<&'w str, String>,
+    node_attrs: &'w HashMap<String, Pairs>,
+    edge_default: &'static str,
+}
+
+impl<'w> Writer<'w> {
+    fn scope(&self, scope: &Scope, graph_id: &str, depth: usize, out: &mut String) {
+        let indent = "  ".repeat(depth);
+        out.push_str(&format!("{}<graph id=\"{}\" edgedefault=\"{}\">\n", indent, escape(graph_id), self.edge_default));
+        self.data(&scope.data, depth + 1, out);
+        for item in scope.items.iter() {
+            match item {
+                Item::Node(id) => self.element(&format!("node id=\"{}\"", escape(id)), "node",
+                                               self.node_attrs.get(id).map_or(&[][..], Vec::as_slice), depth + 1, out),
+                Item::Graph(nested) => {
+                    out.push_str(&format!("{}  <node id=\"{}\">\n", indent, escape(&nested.id)));
+                    self.scope(nested, &format!("{}:", nested.id), depth + 2, out);
+                    out.push_str(&format!("{}  </node>\n", indent));
+                }
+                Item::Edge { source, target, data } => self.element(
+                    &format!("edge source=\"{}\" target=\"{}\"", escape(source), escape(target)), "edge",
+                    data, depth + 1, out),
+            }
+        }
+        out.push_str(&format!("{}</graph>\n", indent));
+    }
+
+    fn element(&self, open: &str, tag: &str, data: &[(String, String)], depth: usize, out: &mut String) {
+        let indent = "  ".repeat(depth);
+        if data.is_empty() {
+            out.push_str(&format!("{}<{}/>\n", indent, open));
+        } else {
+            out.push_str(&format!("{}<{}>\n", indent, open));
+            self.data(data, depth + 1, out);
+            out.push_str(&format!("{}</{}>\n", indent, tag));
+        }
+    }
+
+    fn data(&self, data: &[(String, String)], depth: usize, out: &mut String) {
+        for (key, value) in data.iter() {
+            out.push_str(&format!("{}<data key=\"{}\">{}</data>\n", "  ".repeat(depth), self.keys[key.as_str()], escape(value)));
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
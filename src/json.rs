@@ -0,0 +1,197 @@
+//! Export graphs in the JSON format of `dot -Tjson`, without layout information.
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+use crate::graph::endpoint_ids;
+use crate::{AttrList, AttrType, EdgeNode, Graph, GraphType, Identity, Stmt, StmtList, SubGraph};
+
+/// Convert a graph into the structure `dot -Tjson` produces, so it can be handed to tools that read
+/// graphviz JSON without running `dot`. Since no layout is done, there are no `pos` or `bb` attributes.
+///
+/// - the top level carries `name`, `directed`, `strict`, `_subgraph_cnt` and the graph attributes,
+/// - `objects` lists the subgraphs depth first, followed by the nodes in order of appearance; every
+///   object's `_gvid` is its index in `objects`,
+/// - a subgraph object lists the `_gvid`s of its direct `subgraphs` and of all `nodes` and `edges`
+///   inside of it, nested ones included, and carries its own attributes,
+/// - `edges` has one entry per hop of every edge chain, with the `_gvid`s of its `tail` and `head`;
+///   a subgraph endpoint connects every node in it.
+///
+/// Attribute values are written as strings, like graphviz does; the attributes of repeated node
+/// statements are merged. Anonymous subgraphs are named `%1`, `%2`, ... Default `node`/`edge`
+/// attributes, ports and comments are left out.
+/// ```
+/// use tabbycat::{to_json, EdgeOp, GraphBuilder, GraphType, Identity, StmtList};
+/// let graph = GraphBuilder::default()
+///     .graph_type(GraphType::DiGraph)
+///     .strict(false)
+///     .stmts(StmtList::new()
+///         .add_edge_between(Identity::id("a").unwrap(), Identity::id("b").unwrap(), EdgeOp::Arrow))
+///     .build()
+///     .unwrap();
+/// let json = to_json(&graph);
+/// assert_eq!(true, json["directed"]);
+/// assert_eq!("b", json["objects"][json["edges"][0]["head"].as_u64().unwrap() as usize]["name"]);
+/// ```
+pub fn to_json(graph: &Graph) -> Value {
+    let mut exporter = Exporter::default();
+    let mut top = Map::new();
+    top.insert("name".to_string(), Value::from(graph.id.as_ref().map_or_else(String::new, Identity::unquoted)));
+    top.insert("directed".to_string(), Value::from(graph.graph_type == GraphType::DiGraph));
+    top.insert("strict".to_string(), Value::from(graph.strict));
+    exporter.list(&graph.stmts, &mut top, &[]);
+
+    let offset = exporter.subgraphs.len();
+    let mut objects = Vec::with_capacity(offset + exporter.nodes.len());
+    for (index, subgraph) in exporter.subgraphs.into_iter().enumerate() {
+        let mut object = Map::new();
+        object.insert("_gvid".to_string(), Value::from(index));
+        object.insert("name".to_string(), Value::from(subgraph.name));
+        object.extend(subgraph.attrs);
+        if !subgraph.subgraphs.is_empty() {
+            object.insert("subgraphs".to_string(), Value::from(subgraph.subgraphs));
+        }
+        if !subgraph.nodes.is_empty() {
+            let nodes: Vec<usize> = subgraph.nodes.iter().map(|node| node + offset).collect();
+            object.insert("nodes".to_string(), Value::from(nodes));
+        }
+        if !subgraph.edges.is_empty() {
+            object.insert("edges".to_string(), Value::from(subgraph.edges));
+        }
+        objects.push(Value::Object(object));
+    }
+    for (index, (name, attrs)) in exporter.nodes.into_iter().enumerate() {
+        let mut object = Map::new();
+        object.insert("_gvid".to_string(), Value::from(index + offset));
+        object.insert("name".to_string(), Value::from(name));
+        object.extend(attrs);
+        objects.push(Value::Object(object));
+    }
+    let edges: Vec<Value> = exporter.edges.into_iter()
+        .enumerate()
+        .map(|(index, (tail, head, attrs))| {
+            let mut edge = Map::new();
+            edge.insert("_gvid".to_string(), Value::from(index));
+            edge.insert("tail".to_string(), Value::from(tail + offset));
+            edge.insert("head".to_string(), Value::from(head + offset));
+            edge.extend(attrs);
+            Value::Object(edge)
+        })
+        .collect();
+
+    top.insert("_subgraph_cnt".to_string(), Value::from(offset));
+    top.insert("objects".to_string(), Value::from(objects));
+    top.insert("edges".to_string(), Value::from(edges));
+    Value::Object(top)
+}
+
+#[derive(Default)]
+struct Subgraph {
+    name: String,
+    attrs: Map<String, Value>,
+    subgraphs: Vec<usize>,
+    nodes: Vec<usize>,
+    edges: Vec<usize>,
+}
+
+#[derive(Default)]
+struct Exporter {
+    subgraphs: Vec<Subgraph>,
+    nodes: Vec<(String, Map<String, Value>)>,
+    indices: HashMap<String, usize>,
+    edges: Vec<(usize, usize, Map<String, Value>)>,
+}
+
+fn insert_attrs(attrs: &mut Map<String, Value>, list: &AttrList) {
+    for (k, v) in list.to_string_pairs() {
+        attrs.insert(k, Value::from(v));
+    }
+}
+
+impl Exporter {
+    /// Visit the statements of the graph or of a subgraph, `path` holds the enclosing subgraphs.
+    fn list(&mut self, list: &StmtList, attrs: &mut Map<String, Value>, path: &[usize]) {
+        for stmt in list.0.iter() {
+            match stmt {
+                Stmt::Node { id, attr, .. } => {
+                    let node = self.node(id, path);
+                    if let Some(list) = attr {
+                        insert_attrs(&mut self.nodes[node].1, list);
+                    }
+                }
+                Stmt::Edge(edge) => {
+                    for node in std::iter::once(&edge.node).chain(edge.body.iter().map(|body| &body.node)) {
+                        match node {
+                            EdgeNode::Node { id, .. } => {
+                                self.node(id, path);
+                            }
+                            EdgeNode::SubGraph(sub) => self.subgraph(sub, path),
+                        }
+                    }
+                    let mut edge_attrs = Map::new();
+                    if let Some(list) = &edge.attr {
+                        insert_attrs(&mut edge_attrs, list);
+                    }
+                    let mut from = &edge.node;
+                    for body in edge.body.iter() {
+                        let (mut tails, mut heads) = (Vec::new(), Vec::new());
+                        endpoint_ids(from, &mut tails);
+                        endpoint_ids(&body.node, &mut heads);
+                        for tail in tails.iter() {
+                            for head in heads.iter() {
+                                let (tail, head) = (self.node(tail, path), self.node(head, path));
+                                for &subgraph in path {
+                                    self.subgraphs[subgraph].edges.push(self.edges.len());
+                                }
+                                self.edges.push((tail, head, edge_attrs.clone()));
+                            }
+                        }
+                        from = &body.node;
+                    }
+                }
+                Stmt::Attr(AttrType::Graph, list) => insert_attrs(attrs, list),
+                Stmt::Equation(k, v) => {
+                    attrs.insert(k.unquoted(), Value::from(v.unquoted()));
+                }
+                Stmt::SubGraph(sub) => self.subgraph(sub, path),
+                Stmt::Attr(..) | Stmt::Comment(..) | Stmt::LineComment(..) => {}
+            }
+        }
+    }
+
+    fn subgraph(&mut self, sub: &SubGraph, path: &[usize]) {
+        let index = self.subgraphs.len();
+        let name = match sub {
+            SubGraph::SubGraph { id: Some(id), .. } => id.unquoted(),
+            _ => format!("%{}", index + 1),
+        };
+        self.subgraphs.push(Subgraph { name, ..Subgraph::default() });
+        if let Some(&parent) = path.last() {
+            self.subgraphs[parent].subgraphs.push(index);
+        }
+        let mut attrs = Map::new();
+        let mut nested = path.to_vec();
+        nested.push(index);
+        self.list(sub.stmts(), &mut attrs, &nested);
+        self.subgraphs[index].attrs = attrs;
+    }
+
+    /// The index of the node named by `id`, added to every enclosing subgraph it is not yet part of.
+    fn node(&mut self, id: &Identity, path: &[usize]) -> usize {
+        let name = id.unquoted();
+        let node = match self.indices.get(&name) {
+            Some(&node) => node,
+            None => {
+                self.nodes.push((name.clone(), Map::new()));
+                self.indices.insert(name, self.nodes.len() - 1);
+                self.nodes.len() - 1
+            }
+        };
+        for &subgraph in path {
+            if !self.subgraphs[subgraph].nodes.contains(&node) {
+                self.subgraphs[subgraph].nodes.push(node);
+            }
+        }
+        node
+    }
+}
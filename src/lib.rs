@@ -11,6 +11,7 @@
 //! By enabling this feature, you will be able to write something like:
 //!
 //! ```
+//! # #[cfg(feature = "attributes")] {
 //! use tabbycat::attributes::*;
 //! use tabbycat::AttrList;
 //! let attrlist =  AttrList::new()
@@ -19,12 +20,14 @@
 //!     .new_bracket()
 //!     .add_pair(fillcolor(Color::Blue))
 //!     .add_pair(arrowhead(ArrowShape::Orinv));
-//! assert_eq!("[fontsize=12;label=\"test\";][fillcolor=blue;arrowhead=orinv;]", attrlist.to_string())
+//! assert_eq!("[fontsize=12,label=\"test\"][fillcolor=blue,arrowhead=orinv]", attrlist.to_string())
+//! # }
 //! ```
 //!
 //! # Example
 //!
 //! ```
+//! # #[cfg(feature = "attributes")] {
 //! use tabbycat::attributes::*;
 //! use tabbycat::{AttrList, GraphBuilder, GraphType, Identity, StmtList, Edge, SubGraph};
 //! let graph = GraphBuilder::default()
@@ -43,19 +46,59 @@
 //!     .build()
 //!     .unwrap();
 //! println!("{}", graph);
+//! # }
 //! ```
 //!
 //! This will generate an output like:
 //! ```plaintext
-//! digraph G{A[color=red;];B->C[arrowhead=diamond;];subgraph D{E->F;};}
+//! digraph G {A[color=red];B->C[arrowhead=diamond];subgraph D {E->F;};}
 //! ```
+pub use diff::GraphDiff;
+pub use error::Error;
 pub use graph::*;
+pub use record::RecordLabel;
+pub use validation::ValidationError;
+pub use visit::GraphVisitor;
 
+mod diff;
+mod error;
 mod graph;
+mod record;
+mod validation;
+pub mod visit;
 
 #[cfg(feature = "attributes")]
 pub mod attributes;
 
+#[cfg(feature = "petgraph")]
+mod petgraph_adapter;
+#[cfg(feature = "petgraph")]
+pub use petgraph_adapter::from_petgraph;
+
+#[cfg(feature = "graphml")]
+mod graphml;
+#[cfg(feature = "graphml")]
+pub use graphml::to_graphml;
+
+#[cfg(feature = "mermaid")]
+mod mermaid;
+#[cfg(feature = "mermaid")]
+pub use mermaid::to_mermaid;
+
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "json")]
+pub use json::to_json;
+
+#[cfg(feature = "render")]
+mod render;
+#[cfg(feature = "render")]
+pub use render::Engine;
+
+#[cfg(feature = "macros")]
+#[doc(hidden)]
+pub mod macros;
+
 #[cfg(test)]
 mod test {
     #[cfg(feature = "attributes")]
@@ -77,6 +120,17 @@ mod test {
         assert_eq!("\"123\"", Identity::quoted("123").to_string());
     }
 
+    #[test]
+    fn id_pattern_matches() {
+        let pattern = crate::graph::id_pattern();
+        for valid in ["a", "_", "node_1", "A_b2", "\u{e9}t\u{e9}"].iter() {
+            assert!(pattern.is_match(valid), "{}", valid);
+        }
+        for invalid in ["", "1nvalid", "a-b", "a b", "a.b", "\"a\""].iter() {
+            assert!(!pattern.is_match(invalid), "{}", invalid);
+        }
+    }
+
     #[test]
     fn codegen_port() {
         use crate::Port;
@@ -113,12 +167,13 @@ mod test {
                     .add_pair(label("test"))
                     .add_pair(fillcolor(Color::Blue))
                     .add_pair(arrowhead(ArrowShape::Orinv));
-                Ok(assert_eq!("[name=abc;color=red;][size=12;fontsize=12;label=\"test\";fillcolor=blue;arrowhead=orinv;]", attrlist.to_string()))
+                assert_eq!("[name=abc,color=red][size=12,fontsize=12,label=\"test\",fillcolor=blue,arrowhead=orinv]", attrlist.to_string());
             }
         #[cfg(not(feature = "attributes"))]
             {
-                Ok(assert_eq!("[name=abc;color=red;][size=12;]", attrlist.to_string()))
+                assert_eq!("[name=abc,color=red][size=12]", attrlist.to_string());
             }
+        Ok(())
     }
 
 
@@ -152,7 +207,7 @@ mod test {
                     .add_node(Identity::id("c")?, None, None)
                     .add_node(Identity::id("d")?, None, None)
                 ));
-        assert_eq!("a->b->{c;d;}[color=pink;]", edge.to_string());
+        assert_eq!("a->b->{c;d;}[color=pink]", edge.to_string());
         Ok(())
     }
 
@@ -193,4 +248,2228 @@ mod test {
         println!("{:#}", g);
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn codegen_pretty() -> anyhow::Result<()> {
+        use crate::*;
+        let g = GraphBuilder::default()
+            .graph_type(GraphType::Graph)
+            .strict(false)
+            .id(Identity::id("G")?)
+            .stmts(StmtList::new()
+                .add_node(Identity::id("a")?, None, Some(AttrList::new()
+                    .add(Identity::id("color")?, Identity::id("red")?)))
+                .add_subgraph(SubGraph::subgraph(Some(Identity::id("empty")?), StmtList::new()))
+                .add_subgraph(SubGraph::cluster(StmtList::new()
+                    .add_node(Identity::id("b")?, None, None)
+                    .add_subgraph(SubGraph::cluster(StmtList::new()
+                        .add_edge(Edge::head_node(Identity::id("b")?, None)
+                            .line_to_node(Identity::id("c")?, None)))))))
+            .build()
+            .unwrap();
+        let expected = "\
+graph G {
+  a [color=red];
+  subgraph empty {};
+  {
+    b;
+    {
+      b--c;
+    };
+  };
+}";
+        assert_eq!(expected, g.to_string_pretty(2));
+        assert_eq!(g.to_string_pretty(4), format!("{:#}", g));
+        Ok(())
+    }
+
+    #[test]
+    fn codegen_pretty_aligned() -> anyhow::Result<()> {
+        use crate::*;
+        let attrs = |k, v| -> anyhow::Result<AttrList> {
+            Ok(AttrList::new().add(Identity::id(k)?, Identity::id(v)?))
+        };
+        let g = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .id(Identity::id("G")?)
+            .stmts(StmtList::new()
+                .add_attr(AttrType::Edge, attrs("color", "gray")?)
+                .add_node(Identity::id("a")?, None, Some(attrs("shape", "box")?.add(Identity::id("color")?, Identity::id("red")?)))
+                .add_node(Identity::id("bb")?, Some(Port::compass(Compass::North)), Some(attrs("shape", "circle")?))
+                .add_node(Identity::id("plain")?, None, None)
+                .add_edge(Edge::head_node(Identity::id("a")?, None)
+                    .arrow_to_node(Identity::id("bb")?, None)
+                    .add_attrlist(attrs("style", "dashed")?))
+                .add_subgraph(SubGraph::cluster(StmtList::new()
+                    .add_node(Identity::id("c")?, None, Some(attrs("color", "blue")?))
+                    .add_node(Identity::id("longer")?, None, Some(attrs("color", "green")?)))))
+            .build()
+            .unwrap();
+        let expected = "\
+digraph G {
+  edge  [color=gray];
+  a     [shape=box, color=red];
+  bb:n  [shape=circle];
+  plain;
+  a->bb [style=dashed];
+  {
+    c      [color=blue];
+    longer [color=green];
+  };
+}";
+        assert_eq!(expected, g.to_string_pretty(2));
+        Ok(())
+    }
+
+    #[test]
+    fn assign_element_ids() -> anyhow::Result<()> {
+        use crate::*;
+        let build = || -> anyhow::Result<Graph> {
+            Ok(GraphBuilder::default()
+                .graph_type(GraphType::DiGraph)
+                .strict(false)
+                .id(Identity::id("G")?)
+                .stmts(StmtList::new()
+                    .add_node(Identity::id("a")?, None, None)
+                    .add_edge(Edge::head_node(Identity::id("a")?, None)
+                        .arrow_to_node(Identity::id("b")?, None))
+                    .add_edge(Edge::head_node(Identity::id("a")?, None)
+                        .arrow_to_node(Identity::id("b")?, None))
+                    .add_subgraph(SubGraph::subgraph(Some(Identity::id("s")?), StmtList::new()
+                        .add_edge(Edge::head_node(Identity::id("b")?, None)
+                            .arrow_to_node(Identity::id("c")?, None)))))
+                .build()
+                .unwrap()
+                .assign_element_ids("g_"))
+        };
+        let rendered = build()?.to_string();
+        assert_eq!(rendered, build()?.to_string());
+        assert_eq!("digraph G {a[id=\"g_a\"];a->b[id=\"g_a-b\"];a->b[id=\"g_a-b_2\"];\
+subgraph s {b->c[id=\"g_b-c\"];};b[id=\"g_b\"];c[id=\"g_c\"];}", rendered);
+        let ids: Vec<&str> = rendered.split("id=").skip(1).map(|x| x.split(']').next().unwrap()).collect();
+        let unique: std::collections::HashSet<&&str> = ids.iter().collect();
+        assert_eq!(6, ids.len());
+        assert_eq!(ids.len(), unique.len());
+
+        // ids that only differ in characters that are replaced still get distinct element ids
+        let rendered = GraphBuilder::default()
+            .graph_type(GraphType::Graph)
+            .strict(false)
+            .id(Identity::id("G")?)
+            .stmts(StmtList::new()
+                .add_node(Identity::quoted("a b"), None, None)
+                .add_node(Identity::id("a_b")?, None, None)
+                .add_edge(Edge::head_node(Identity::quoted("a b"), None)
+                    .line_to_node(Identity::quoted("c>d"), None)))
+            .build()
+            .unwrap()
+            .assign_element_ids("n_")
+            .to_string();
+        assert_eq!("graph G {\"a b\"[id=\"n_a_b\"];a_b[id=\"n_a_b_2\"];\"a b\"--\"c>d\"[id=\"n_a_b-c_d\"];\
+\"c>d\"[id=\"n_c_d\"];}", rendered);
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() -> anyhow::Result<()> {
+        use crate::*;
+        let g = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(true)
+            .id(Identity::id("G")?)
+            .stmts(StmtList::new()
+                .add_attr(AttrType::Node, AttrList::new().add(Identity::id("shape")?, Identity::id("box")?))
+                .add_node(Identity::id("a")?, Some(Port::id_compass(Identity::id("p")?, Compass::North)),
+                          Some(AttrList::new().add(Identity::id("label")?, Identity::quoted("A node"))))
+                .add_equation(Identity::id("rank")?, Identity::id("same")?)
+                .add_edge(Edge::head_node(Identity::id("a")?, None)
+                    .arrow_to_node(Identity::from(1.5), None)
+                    .arrow_to_subgraph(SubGraph::cluster(StmtList::new()
+                        .add_node(Identity::from(-3), None, None)))
+                    .add_attribute(Identity::id("weight")?, Identity::from(2_u8)))
+                .add_subgraph(SubGraph::subgraph(Some(Identity::id("s")?), StmtList::new()
+                    .add_node(Identity::OwnedQuoted("owned".to_string()), None, None))))
+            .build()
+            .unwrap();
+        let json = serde_json::to_string(&g)?;
+        let back: Graph = serde_json::from_str(&json)?;
+        assert_eq!(g.to_string(), back.to_string());
+        assert_eq!(json, serde_json::to_string(&back)?);
+        Ok(())
+    }
+
+    #[test]
+    fn subgraph_builder() -> anyhow::Result<()> {
+        use crate::*;
+        let sub = SubGraphBuilder::named(Identity::id("cluster_x")?)
+            .label("Group X")
+            .add_node(Identity::id("a")?, None, None)
+            .add_node(Identity::id("b")?, None, None)
+            .add_edge(Edge::head_node(Identity::id("a")?, None)
+                .arrow_to_node(Identity::id("b")?, None))
+            .build();
+        assert_eq!("subgraph cluster_x {label=\"Group X\";a;b;a->b;}", sub.to_string());
+        let anonymous = SubGraphBuilder::cluster()
+            .add_node(Identity::id("c")?, None, None)
+            .build();
+        assert_eq!("{c;}", anonymous.to_string());
+        Ok(())
+    }
+
+    #[cfg(feature = "petgraph")]
+    #[test]
+    fn from_petgraph() -> anyhow::Result<()> {
+        use crate::*;
+        let mut g = ::petgraph::Graph::<&str, u32>::new();
+        let a = g.add_node("a");
+        let b = g.add_node("b");
+        let c = g.add_node("c");
+        g.add_edge(a, b, 1);
+        g.add_edge(b, c, 2);
+        let graph = crate::from_petgraph(&g,
+                                         |n| (Identity::String(n), None),
+                                         |w| Some(AttrList::new().add(Identity::String("weight"), Identity::from(*w))));
+        let dot = graph.to_string();
+        assert!(dot.starts_with("digraph {"));
+        assert!(dot.contains("a->b[weight=1]"));
+        assert!(dot.contains("b->c[weight=2]"));
+        assert!(!dot.contains("--"));
+        Ok(())
+    }
+
+    #[test]
+    fn emphasize_nodes() -> anyhow::Result<()> {
+        use crate::*;
+        use std::collections::HashSet;
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_node(Identity::id("a")?, None, Some(AttrList::new().add(Identity::id("color")?, Identity::id("blue")?).add(Identity::id("shape")?, Identity::id("box")?)))
+                .add_node(Identity::id("c")?, None, None)
+                .add_edge(Edge::head_node(Identity::id("a")?, None)
+                    .arrow_to_node(Identity::id("b")?, None)))
+            .build()
+            .unwrap();
+        let nodes: HashSet<_> = vec![Identity::id("a")?, Identity::id("b")?, Identity::id("d")?].into_iter().collect();
+        let attrs = AttrList::new()
+            .add(Identity::id("penwidth")?, Identity::from(3))
+            .add(Identity::id("peripheries")?, Identity::from(2))
+            .add(Identity::id("color")?, Identity::id("red")?);
+        assert_eq!(
+            "digraph {a[color=red,shape=box,penwidth=3,peripheries=2];c;a->b;b[penwidth=3,peripheries=2,color=red];}",
+            graph.emphasize_nodes(&nodes, attrs).to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn render_to() -> anyhow::Result<()> {
+        use crate::*;
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::Graph)
+            .strict(true)
+            .id(Identity::id("G")?)
+            .stmts(StmtList::new()
+                .add_node(Identity::id("a")?, None, None)
+                .add_edge(Edge::head_node(Identity::id("a")?, None)
+                    .line_to_node(Identity::id("b")?, None)
+                    .add_attribute(Identity::id("color")?, Identity::id("red")?)))
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        graph.render_to(&mut out)?;
+        assert_eq!(graph.to_string(), String::from_utf8(out)?);
+        Ok(())
+    }
+
+    #[cfg(feature = "attributes")]
+    #[test]
+    fn attribute_rankdir() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        assert_eq!("[rankdir=LR]", AttrList::new().add_pair(rankdir(RankDir::LR)).to_string());
+        assert_eq!("TB", Identity::from(RankDir::TB).to_string());
+        assert_eq!("RL", Identity::from(RankDir::RL).to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn with_typography() -> anyhow::Result<()> {
+        use crate::*;
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_edge(Edge::head_node(Identity::id("a")?, None)
+                    .arrow_to_node(Identity::id("b")?, None)))
+            .build()
+            .unwrap()
+            .with_typography("Helvetica Neue", 10.5);
+        assert_eq!(
+            "digraph {graph [fontname=\"Helvetica Neue\",fontsize=10.5];\
+             node [fontname=\"Helvetica Neue\",fontsize=10.5];\
+             edge [fontname=\"Helvetica Neue\",fontsize=10.5];a->b;}",
+            graph.to_string());
+        Ok(())
+    }
+
+    #[cfg(feature = "attributes")]
+    #[test]
+    fn attribute_style() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        assert_eq!("[style=dashed]", AttrList::new().add_pair(style(Style::Dashed)).to_string());
+        assert_eq!("[style=\"filled,rounded\"]",
+                   AttrList::new().add_pair(styles(vec![Style::Filled, Style::Rounded])).to_string());
+        assert_eq!("[style=\"bold\"]", AttrList::new().add_pair(styles(Some(Style::Bold))).to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn attrlist_string_pairs() -> anyhow::Result<()> {
+        use crate::*;
+        let pairs = vec![
+            ("shape".to_string(), "box".to_string()),
+            ("label".to_string(), "hello world".to_string()),
+            ("penwidth".to_string(), "2.5".to_string()),
+        ];
+        let list = AttrList::from_string_pairs(pairs.iter().map(|(k, v)| (k, v)));
+        assert_eq!("[shape=box,label=\"hello world\",penwidth=2.5]", list.to_string());
+        assert_eq!(pairs, list.to_string_pairs());
+        assert_eq!(list, AttrList::from_string_pairs(list.to_string_pairs()));
+        assert_eq!(list, AttrList::from_string_pairs(vec![("shape", "box"), ("label", "hello world"), ("penwidth", "2.5")]));
+        Ok(())
+    }
+
+    #[test]
+    fn identity_variants_same_node() -> anyhow::Result<()> {
+        use crate::*;
+        use std::collections::HashSet;
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(true)
+            .stmts(StmtList::new()
+                .add_node(Identity::quoted("a"), None, Some(AttrList::new().add(Identity::id("color")?, Identity::id("red")?)))
+                .add_edge(Edge::head_node(Identity::id("a")?, None).arrow_to_node(Identity::Owned("b".to_string()), None))
+                .add_edge(Edge::head_node(Identity::Owned("a".to_string()), None).arrow_to_node(Identity::quoted("b"), None))
+                .add_edge(Edge::head_node(Identity::id("b")?, None).arrow_to_node(Identity::from(1), None))
+                .add_node(Identity::quoted("1"), None, None))
+            .build()
+            .unwrap();
+        assert_eq!(3, graph.node_count());
+        assert!(graph.node_attrs(&Identity::Owned("a".to_string())).is_some());
+        assert_eq!("strict digraph {\"a\"[color=red];a->b;b->1;\"1\";}", graph.clone().dedup_strict().to_string());
+        let near = graph.neighborhood(&Identity::id("a")?, 1);
+        assert_eq!(2, near.node_count());
+        let emphasized = graph.emphasize_nodes(&vec![Identity::id("a")?].into_iter().collect::<HashSet<_>>(),
+                                               AttrList::new().add(Identity::id("penwidth")?, Identity::from(2)));
+        assert_eq!("strict digraph {\"a\"[color=red,penwidth=2];a->b;a->\"b\";b->1;\"1\";}", emphasized.to_string());
+        let escaped = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(true)
+            .stmts(StmtList::new()
+                .add_node(Identity::quoted(r"a\|b"), None, None)
+                .add_node(Identity::EscString(r"a\|b".to_string()), None, None)
+                .add_node(Identity::EscString(r"a\\|b".to_string()), None, None))
+            .build()
+            .unwrap();
+        assert_eq!(2, escaped.node_count());
+        assert_eq!(2, escaped.nodes_iter().collect::<HashSet<_>>().len());
+        assert_eq!(r#"strict digraph {"a\\|b";"a\|b";}"#, escaped.dedup_strict().to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn edge_builder() -> anyhow::Result<()> {
+        use crate::*;
+        let attrs = AttrList::new().add(Identity::id("color")?, Identity::id("red")?);
+        let built = EdgeBuilder::default()
+            .head(EdgeNode::Node { id: Identity::id("a")?, port: Some(Port::compass(Compass::North)) })
+            .arrow_to_node(Identity::id("b")?, None)
+            .arrow_to_subgraph(SubGraph::cluster(StmtList::new().add_node(Identity::id("c")?, None, None)))
+            .attr(attrs.clone())
+            .build()
+            .unwrap();
+        let chained = Edge::head_node(Identity::id("a")?, Some(Port::compass(Compass::North)))
+            .arrow_to_node(Identity::id("b")?, None)
+            .arrow_to_subgraph(SubGraph::cluster(StmtList::new().add_node(Identity::id("c")?, None, None)))
+            .add_attrlist(attrs);
+        assert_eq!(chained.to_string(), built.to_string());
+        assert!(EdgeBuilder::default().arrow_to_node(Identity::id("b")?, None).build().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn neighborhood() -> anyhow::Result<()> {
+        use crate::*;
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .id(Identity::id("G")?)
+            .stmts(StmtList::new()
+                .add_attr(AttrType::Node, AttrList::new().add(Identity::id("shape")?, Identity::id("box")?))
+                .add_node(Identity::id("b")?, None, Some(AttrList::new().add(Identity::id("color")?, Identity::id("red")?)))
+                .add_node(Identity::id("d")?, None, None)
+                .add_edge(Edge::head_node(Identity::id("a")?, None)
+                    .arrow_to_node(Identity::id("b")?, None)
+                    .arrow_to_node(Identity::id("c")?, None)
+                    .arrow_to_node(Identity::id("d")?, None))
+                .add_subgraph(SubGraph::subgraph(Some(Identity::id("cluster_x")?), StmtList::new()
+                    .add_equation(Identity::id("label")?, Identity::quoted("x"))
+                    .add_edge(Edge::head_node(Identity::id("x")?, None)
+                        .arrow_to_node(Identity::id("b")?, None))))
+                .add_subgraph(SubGraph::cluster(StmtList::new()
+                    .add_attr(AttrType::Edge, AttrList::new().add(Identity::id("color")?, Identity::id("blue")?))
+                    .add_edge(Edge::head_node(Identity::id("d")?, None)
+                        .arrow_to_node(Identity::id("e")?, None)))))
+            .build()
+            .unwrap();
+        assert_eq!(
+            "digraph G {node [shape=box];b[color=red];a->b;b->c;\
+             subgraph cluster_x {label=\"x\";x->b;};}",
+            graph.neighborhood(&Identity::id("b")?, 1).to_string());
+        assert_eq!(
+            "digraph G {node [shape=box];b[color=red];}",
+            graph.neighborhood(&Identity::id("b")?, 0).to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn stmtlist_collect() -> anyhow::Result<()> {
+        use crate::*;
+        let stmts = vec![
+            Stmt::Node { id: Identity::id("b")?, port: None, attr: None },
+            Stmt::Node { id: Identity::id("a")?, port: None, attr: None },
+            Stmt::Equation(Identity::id("rankdir")?, Identity::id("LR")?),
+        ];
+        assert_eq!("", StmtList::default().to_string());
+        let mut list: StmtList = stmts.into_iter().collect();
+        assert_eq!("b;a;rankdir=LR;", list.to_string());
+        Extend::extend(&mut list, vec![Stmt::Edge(Edge::head_node(Identity::id("a")?, None)
+            .arrow_to_node(Identity::id("b")?, None))]);
+        assert_eq!("b;a;rankdir=LR;a->b;", list.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn attrlist_collect() -> anyhow::Result<()> {
+        use crate::*;
+        let list: AttrList = vec![("a", "b"), ("c", "d"), ("e", "f")].into_iter()
+            .map(|(k, v)| (Identity::String(k), Identity::String(v)))
+            .collect();
+        assert_eq!("[a=b,c=d,e=f]", list.to_string());
+        assert_eq!("", AttrList::default().to_string());
+        let empty: AttrList = std::iter::empty().collect();
+        assert_eq!("", empty.to_string());
+        Ok(())
+    }
+
+    #[cfg(feature = "macros")]
+    #[test]
+    fn dot_macro() -> anyhow::Result<()> {
+        use crate::*;
+        let graph = dot!(graph {
+            edge [color = blue; penwidth = 2];
+            a -- b -- "c d";
+            b [shape = circle, width = 0.5];
+        });
+        assert_eq!("graph {edge [color=blue,penwidth=2];a--b--\"c d\";b[shape=circle,width=0.5];}", graph.to_string());
+        let graph = dot!(strict digraph { a -> b [color = red]; });
+        let expected = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(true)
+            .stmts(StmtList::new()
+                .add_edge(Edge::head_node(Identity::id("a")?, None)
+                    .arrow_to_node(Identity::id("b")?, None)
+                    .add_attribute(Identity::id("color")?, Identity::id("red")?)))
+            .build()
+            .unwrap();
+        assert_eq!(expected.to_string(), graph.to_string());
+        assert_eq!("digraph {}", dot!(digraph {}).to_string());
+        let graph = dot!(graph { ranksep = -0.5; a [width = -1, height = -2.5; label = "node"]; });
+        assert_eq!("graph {ranksep=-0.5;a[width=-1,height=-2.5,label=\"node\"];}", graph.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn record_label() -> anyhow::Result<()> {
+        use crate::*;
+        let label = RecordLabel::new()
+            .port_field("f0", "left")
+            .port_field("f1", "right");
+        let attrs = AttrList::new()
+            .add(Identity::id("shape")?, Identity::id("record")?)
+            .add(Identity::id("label")?, label.into());
+        assert_eq!("[shape=record,label=\"<f0> left|<f1> right\"]", attrs.to_string());
+        let nested = RecordLabel::new()
+            .field("hello\\world")
+            .nested(RecordLabel::new()
+                .field("b")
+                .nested(RecordLabel::new().field("c").port_field("here", "d").field("e"))
+                .field("f"))
+            .port_field("p", "")
+            .field("{x} <y> \"z\"");
+        assert_eq!(r#""hello\\world|{b|{c|<here> d|e}|f}|<p>|\{x\} \<y\> \"z\"""#,
+                   Identity::from(nested).to_string());
+        Ok(())
+    }
+
+    #[cfg(feature = "attributes")]
+    #[test]
+    fn attribute_color_list() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        let gradient = ColorList::new(Color::Red, Some(0.3))?.add(Color::Blue, None)?;
+        assert_eq!("[fillcolor=\"red;0.3:blue\"]", AttrList::new().add_pair(fillcolor(gradient)).to_string());
+        let stripes = ColorList::new(Color::Rgb(255, 255, 255), None)?.add(Color::Black, Some(0.5))?;
+        assert_eq!("[color=\"#ffffff:black;0.5\"]", AttrList::new().add_pair(color(stripes)).to_string());
+        let shades = ColorList::new(Color::Rgba(1, 2, 3, 4), Some(0.25))?.add(Color::HSV(0.5, 1.0, 0.25), Some(0.75))?;
+        assert_eq!("[color=\"#01020304;0.25:0.5,+1,+0.25;0.75\"]", AttrList::new().add_pair(color(shades)).to_string());
+        assert_eq!("[bgcolor=red]", AttrList::new().add_pair(bgcolor(Color::Red)).to_string());
+        assert_eq!(Error::InvalidWeight(-0.5), ColorList::new(Color::Red, Some(-0.5)).unwrap_err());
+        assert_eq!(Error::InvalidWeight(2.0), ColorList::new(Color::Red, Some(2.0)).unwrap_err());
+        assert!(matches!(ColorList::new(Color::Red, Some(f32::NAN)), Err(Error::NonFiniteNumber(_))));
+        assert_eq!(Error::InvalidWeight(1.5), ColorList::new(Color::Red, Some(0.5))?.add(Color::Blue, Some(1.0)).unwrap_err());
+        Ok(())
+    }
+
+    #[test]
+    fn codegen_comment() -> anyhow::Result<()> {
+        use crate::*;
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_comment("inputs")
+                .add_node(Identity::id("a")?, None, None)
+                .add_comment(format!("{} */ tricky", 2))
+                .add_edge(Edge::head_node(Identity::id("a")?, None)
+                    .arrow_to_node(Identity::id("b")?, None)))
+            .build()
+            .unwrap();
+        assert_eq!("digraph {/* inputs */a;/* 2 * / tricky */a->b;}", graph.to_string());
+        assert_eq!("digraph {\n  /* inputs */\n  a;\n  /* 2 * / tricky */\n  a->b;\n}", graph.to_string_pretty(2));
+        Ok(())
+    }
+
+    #[test]
+    fn owned_identity() -> anyhow::Result<()> {
+        use crate::*;
+        let stmts = (0..3).fold(StmtList::new(), |list, i| {
+            list.add_node(Identity::Owned(format!("node_{}", i)), None,
+                          Some(AttrList::new().add(Identity::String("label"), Identity::OwnedQuoted(format!("Node #{}", i)))))
+        });
+        assert_eq!("node_0[label=\"Node #0\"];node_1[label=\"Node #1\"];node_2[label=\"Node #2\"];", stmts.to_string());
+        assert_eq!(Identity::String("a").to_string(), Identity::Owned("a".to_string()).to_string());
+        assert_eq!(Identity::Quoted("a b").to_string(), Identity::OwnedQuoted("a b".to_string()).to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn validate_edge_op() -> anyhow::Result<()> {
+        use crate::*;
+        let digraph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_edge(Edge::head_node(Identity::id("a")?, None)
+                    .arrow_to_node(Identity::id("b")?, None))
+                .add_subgraph(SubGraph::cluster(StmtList::new()
+                    .add_edge(Edge::head_node(Identity::id("b")?, None)
+                        .arrow_to_node(Identity::id("c")?, None)
+                        .line_to_node(Identity::id("d")?, None)))))
+            .build()
+            .unwrap();
+        match digraph.validate().unwrap_err().as_slice() {
+            [ValidationError::EdgeOpMismatch { graph_type: GraphType::DiGraph, edge }] => assert_eq!("b->c--d", edge),
+            errors => panic!("unexpected errors: {:?}", errors),
+        }
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::Graph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_edge(Edge::head_node(Identity::id("a")?, None)
+                    .arrow_to_node(Identity::id("b")?, None))
+                .add_edge(Edge::head_node(Identity::id("b")?, None)
+                    .line_to_node(Identity::id("c")?, None)))
+            .build()
+            .unwrap();
+        let errors = graph.validate().unwrap_err();
+        assert_eq!(1, errors.len());
+        assert_eq!("directed edge operator `->` in an undirected graph: a->b", errors[0].to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn dedup_strict() -> anyhow::Result<()> {
+        use crate::*;
+        let edge = |a, b| -> anyhow::Result<Edge> {
+            Ok(Edge::head_node(Identity::id(a)?, None).line_to_node(Identity::id(b)?, None))
+        };
+        let stmts = StmtList::new()
+            .add_node(Identity::id("a")?, None, Some(AttrList::new()
+                .add(Identity::id("color")?, Identity::id("red")?)
+                .add(Identity::id("shape")?, Identity::id("box")?)))
+            .add_edge(edge("a", "b")?)
+            .add_edge(edge("b", "a")?
+                .add_attribute(Identity::id("color")?, Identity::id("blue")?)
+                .add_attribute(Identity::id("style")?, Identity::id("bold")?))
+            .add_node(Identity::id("a")?, None, Some(AttrList::new().add(Identity::id("color")?, Identity::id("green")?)))
+            .add_node(Identity::id("a")?, None, None)
+            .add_edge(edge("a", "b")?.add_attribute(Identity::id("color")?, Identity::id("black")?))
+            .add_edge(edge("b", "a")?)
+            .add_edge(edge("a", "c")?);
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::Graph)
+            .strict(true)
+            .stmts(stmts)
+            .build()
+            .unwrap();
+        assert_eq!("strict graph {a[color=green];a--b[color=black];a--c;}", graph.dedup_strict().to_string());
+        let digraph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(true)
+            .stmts(StmtList::new()
+                .add_edge(Edge::head_node(Identity::id("a")?, None).arrow_to_node(Identity::id("b")?, None))
+                .add_edge(Edge::head_node(Identity::id("b")?, None).arrow_to_node(Identity::id("a")?, None))
+                .add_edge(Edge::head_node(Identity::id("a")?, None).arrow_to_node(Identity::id("b")?, None)))
+            .build()
+            .unwrap();
+        assert_eq!("strict digraph {a->b;b->a;}", digraph.dedup_strict().to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn graph_eq() -> anyhow::Result<()> {
+        use crate::*;
+        let build = |label: &'static str| -> anyhow::Result<Graph<'static>> {
+            Ok(GraphBuilder::default()
+                .graph_type(GraphType::DiGraph)
+                .strict(false)
+                .id(Identity::id("G")?)
+                .stmts(StmtList::new()
+                    .add_node(Identity::id("a")?, None, Some(AttrList::new().add(Identity::id("label")?, Identity::quoted(label))))
+                    .add_edge(Edge::head_node(Identity::id("a")?, Some(Port::compass(Compass::South)))
+                        .arrow_to_node(Identity::id("b")?, None)
+                        .add_attribute(Identity::id("weight")?, Identity::from(0.5))))
+                .build()
+                .unwrap())
+        };
+        assert_eq!(build("x")?, build("x")?);
+        assert_ne!(build("x")?, build("y")?);
+        assert_eq!(Identity::from(f64::NAN), Identity::from(f64::NAN));
+        assert_ne!(Identity::from(0.0), Identity::from(-0.0));
+        assert_eq!(Identity::String("a"), Identity::Quoted("a"));
+        assert_eq!(Identity::Owned("1".to_string()), Identity::from(1u8));
+        Ok(())
+    }
+
+    #[cfg(feature = "attributes")]
+    #[test]
+    fn attribute_dir() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        assert_eq!("[dir=both]", AttrList::new().add_pair(dir(Dir::Both)).to_string());
+        assert_eq!("[dir=back]", AttrList::new().add_pair(dir(DirType::Back)).to_string());
+        assert_eq!("forward", Identity::from(Dir::Forward).to_string());
+        assert_eq!("none", Identity::from(Dir::None).to_string());
+        Ok(())
+    }
+
+    #[cfg(feature = "attributes")]
+    #[test]
+    fn attribute_splines() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        assert_eq!("[splines=ortho]", AttrList::new().add_pair(splines(Splines::Ortho)).to_string());
+        assert_eq!("[splines=polyline]", AttrList::new().add_pair(splines(Splines::Polyline)).to_string());
+        assert_eq!("[splines=false]", AttrList::new().add_pair(splines(Splines::False)).to_string());
+        assert_eq!("[splines=true]", AttrList::new().add_pair(splines(Splines::from(true))).to_string());
+        assert_eq!(Identity::Bool(true), Identity::from(Splines::True));
+        Ok(())
+    }
+
+    #[cfg(feature = "attributes")]
+    #[test]
+    fn subgraph_with_rank() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_edge(Edge::head_node(Identity::id("a")?, None).arrow_to_node(Identity::id("c")?, None))
+                .add_subgraph(SubGraph::with_rank(Rank::Same, vec![Identity::id("a")?, Identity::id("b")?]))
+                .add_subgraph(SubGraph::with_rank(RankType::Sink, Some(Identity::id("c")?))))
+            .build()
+            .unwrap();
+        assert_eq!("digraph {a->c;{rank=same;a;b;};{rank=sink;c;};}", graph.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn codegen_non_finite() -> anyhow::Result<()> {
+        use crate::*;
+        assert_eq!("\"NaN\"", Identity::from(f64::NAN).to_string());
+        assert_eq!("\"inf\"", Identity::from(f32::INFINITY).to_string());
+        assert_eq!("\"-inf\"", Identity::from(f64::NEG_INFINITY).to_string());
+        assert_eq!("1.5", Identity::finite(1.5)?.to_string());
+        assert!(Identity::finite(f64::NAN).is_err());
+        assert!(Identity::finite(f64::INFINITY).is_err());
+        assert!(Identity::finite(f64::NEG_INFINITY).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn codegen_float_stable() -> anyhow::Result<()> {
+        use crate::*;
+        assert_eq!("0.30000000000000004", Identity::Double(0.1 + 0.2).to_string());
+        assert_eq!("0.3", Identity::Float(0.1 + 0.2).to_string());
+        assert_eq!("0.1", Identity::Double(0.1).to_string());
+        assert_eq!("2", Identity::Double(2.0).to_string());
+        assert_eq!("0.0000001", Identity::Double(1e-7).to_string());
+        assert_eq!("10000000000000000000000", Identity::Double(1e22).to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn iterate_by_ref() -> anyhow::Result<()> {
+        use crate::*;
+        let attrs = AttrList::new()
+            .add(Identity::id("a")?, Identity::id("b")?)
+            .new_bracket()
+            .add(Identity::id("c")?, Identity::id("d")?)
+            .add(Identity::id("e")?, Identity::id("f")?);
+        let stmts = StmtList::new()
+            .add_node(Identity::id("x")?, None, Some(attrs.clone()))
+            .add_node(Identity::id("y")?, None, None)
+            .add_equation(Identity::id("rankdir")?, Identity::id("LR")?);
+        let mut nodes = 0;
+        for stmt in &stmts {
+            if let Stmt::Node { .. } = stmt {
+                nodes += 1;
+            }
+        }
+        assert_eq!(2, nodes);
+        assert_eq!(3, (&stmts).into_iter().count());
+        assert_eq!(vec![1, 2], (&attrs).into_iter().map(Vec::len).collect::<Vec<_>>());
+        assert_eq!("x[a=b][c=d,e=f];y;rankdir=LR;", stmts.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn walk_and_count() -> anyhow::Result<()> {
+        use crate::*;
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_node(Identity::id("a")?, None, None)
+                .add_edge(Edge::head_node(Identity::id("a")?, None)
+                    .arrow_to_node(Identity::id("b")?, None)
+                    .arrow_to_subgraph(SubGraph::cluster(StmtList::new()
+                        .add_node(Identity::id("c")?, None, None)
+                        .add_node(Identity::id("d")?, None, None))))
+                .add_subgraph(SubGraph::subgraph(Some(Identity::id("cluster_0")?), StmtList::new()
+                    .add_equation(Identity::id("label")?, Identity::quoted("zero"))
+                    .add_subgraph(SubGraph::cluster(StmtList::new()
+                        .add_edge(Edge::head_node(Identity::id("e")?, None)
+                            .arrow_to_node(Identity::id("a")?, None)))))))
+            .build()
+            .unwrap();
+        let walked: Vec<_> = graph.walk_stmts().map(|stmt| stmt.to_string()).collect();
+        assert_eq!(vec!["a", "a->b->{c;d;}", "c", "d", "subgraph cluster_0 {label=\"zero\";{e->a;};}",
+                        "label=\"zero\"", "{e->a;}", "e->a"], walked);
+        assert_eq!(5, graph.node_count());
+        assert_eq!(4, graph.edge_count());
+        Ok(())
+    }
+
+    #[cfg(feature = "attributes")]
+    #[test]
+    fn attribute_fontcolor() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        assert_eq!("[fontcolor=red]", AttrList::new().add_pair(fontcolor(Color::Red)).to_string());
+        let cluster = SubGraph::subgraph(Some(Identity::id("cluster_0")?), StmtList::new()
+            .add_attr(AttrType::Graph, AttrList::new().add_pair(bgcolor(Color::Lightgrey)).add_pair(fontcolor(Color::White))));
+        assert_eq!("subgraph cluster_0 {graph [bgcolor=lightgrey,fontcolor=white];}", cluster.to_string());
+        Ok(())
+    }
+
+    #[cfg(feature = "attributes")]
+    #[test]
+    fn attribute_fixed_size() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        let list = AttrList::new().add_pair(shape(Shape::Box)).extend(fixed_size(1.5, 0.5));
+        assert_eq!("[shape=box,fixedsize=true,width=1.5,height=0.5]", list.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn codegen_attr_separator() -> anyhow::Result<()> {
+        use crate::*;
+        let list = AttrList::new()
+            .add(Identity::id("a")?, Identity::id("b")?)
+            .add(Identity::id("c")?, Identity::id("d")?);
+        assert_eq!("[a=b,c=d]", list.to_string());
+        assert_eq!("[a=b, c=d]", format!("{:4}", list));
+        Ok(())
+    }
+
+    #[test]
+    fn stmtlist_add_nodes() -> anyhow::Result<()> {
+        use crate::*;
+        let list = StmtList::new()
+            .add_node(Identity::id("start")?, None, None)
+            .add_nodes((1..=5).map(Identity::from));
+        assert_eq!(6, (&list).into_iter().count());
+        assert_eq!("start;1;2;3;4;5;", list.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn stmtlist_add_edge_between() -> anyhow::Result<()> {
+        use crate::*;
+        let list = StmtList::new()
+            .add_edge_between(Identity::id("a")?, Identity::id("b")?, EdgeOp::Arrow)
+            .add_edge_between(Identity::id("b")?, Identity::id("c")?, EdgeOp::Line);
+        assert_eq!("a->b;b--c;", list.to_string());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "graphml")]
+    fn graphml_export() -> anyhow::Result<()> {
+        use crate::*;
+        let stmts = StmtList::new()
+            .add_equation(Identity::id("rankdir")?, Identity::id("LR")?)
+            .add_node(Identity::id("a")?, None, Some(AttrList::new().add(Identity::id("label")?, Identity::Quoted("<a & b>"))))
+            .add_edge(Edge::head_node(Identity::id("a")?, None)
+                .arrow_to_node(Identity::id("b")?, None)
+                .arrow_to_node(Identity::id("c")?, None)
+                .add_attrlist(AttrList::new().add(Identity::id("color")?, Identity::id("red")?)))
+            .add_subgraph(SubGraph::subgraph(Some(Identity::id("cluster_x")?), StmtList::new()
+                .add_edge_between(Identity::id("c")?, Identity::id("d")?, EdgeOp::Arrow)));
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .id(Identity::id("G")?)
+            .stmts(stmts.clone())
+            .build()
+            .unwrap();
+        let xml = to_graphml(&graph);
+        let document = roxmltree::Document::parse(&xml)?;
+        let root = document.root_element();
+        assert_eq!("graphml", root.tag_name().name());
+        assert_eq!(Some("http://graphml.graphdrawing.org/xmlns"), root.tag_name().namespace());
+
+        let keys: std::collections::HashMap<_, _> = root.children()
+            .filter(|n| n.has_tag_name("key"))
+            .map(|n| (n.attribute("attr.name").unwrap(), n.attribute("id").unwrap()))
+            .collect();
+        assert_eq!(3, keys.len());
+
+        let top = root.children().find(|n| n.has_tag_name("graph")).unwrap();
+        assert_eq!(Some("G"), top.attribute("id"));
+        assert_eq!(Some("directed"), top.attribute("edgedefault"));
+        let data = |n: roxmltree::Node, key: &str| n.children()
+            .find(|d| d.has_tag_name("data") && d.attribute("key") == Some(keys[key]))
+            .and_then(|d| d.text().map(str::to_string));
+        assert_eq!(Some("LR".to_string()), data(top, "rankdir"));
+
+        let nodes: Vec<_> = top.children().filter(|n| n.has_tag_name("node")).collect();
+        let ids: Vec<_> = nodes.iter().map(|n| n.attribute("id").unwrap()).collect();
+        assert_eq!(vec!["a", "b", "c", "cluster_x"], ids);
+        assert_eq!(Some("<a & b>".to_string()), data(nodes[0], "label"));
+
+        let edges: Vec<_> = top.children()
+            .filter(|n| n.has_tag_name("edge"))
+            .map(|n| (n.attribute("source").unwrap(), n.attribute("target").unwrap(), data(n, "color")))
+            .collect();
+        assert_eq!(vec![("a", "b", Some("red".to_string())), ("b", "c", Some("red".to_string()))], edges);
+
+        let nested = nodes[3].children().find(|n| n.has_tag_name("graph")).unwrap();
+        assert_eq!(Some("cluster_x:"), nested.attribute("id"));
+        let nested_nodes: Vec<_> = nested.children()
+            .filter(|n| n.has_tag_name("node"))
+            .map(|n| n.attribute("id").unwrap())
+            .collect();
+        assert_eq!(vec!["d"], nested_nodes);
+        assert_eq!(1, nested.children().filter(|n| n.has_tag_name("edge")).count());
+
+        let undirected = GraphBuilder::default()
+            .graph_type(GraphType::Graph)
+            .strict(false)
+            .stmts(stmts)
+            .build()
+            .unwrap();
+        let xml = to_graphml(&undirected);
+        let document = roxmltree::Document::parse(&xml)?;
+        let top = document.root_element().children().find(|n| n.has_tag_name("graph")).unwrap();
+        assert_eq!(Some("undirected"), top.attribute("edgedefault"));
+
+        let clashing = GraphBuilder::default()
+            .graph_type(GraphType::Graph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_subgraph(SubGraph::subgraph(Some(Identity::id("a")?), StmtList::new()
+                    .add_node(Identity::id("a")?, None, None)))
+                .add_subgraph(SubGraph::subgraph(Some(Identity::id("a")?), StmtList::new()))
+                .add_subgraph(SubGraph::cluster(StmtList::new()))
+                .add_node(Identity::id("_subgraph1")?, None, None))
+            .build()
+            .unwrap();
+        let xml = to_graphml(&clashing);
+        let document = roxmltree::Document::parse(&xml)?;
+        let ids: Vec<_> = document.descendants()
+            .filter(|n| n.has_tag_name("node") || n.has_tag_name("graph"))
+            .map(|n| n.attribute("id").unwrap())
+            .collect();
+        assert_eq!(vec!["G", "a_1", "a_1:", "a", "a_2", "a_2:", "_subgraph2", "_subgraph2:", "_subgraph1"], ids);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    fn attribute_label_lines() -> anyhow::Result<()> {
+        use crate::*;
+        let list = AttrList::new()
+            .add_pair(label_lines(&["a", "b"], Align::Left));
+        assert_eq!(r#"[label="a\lb\l"]"#, list.to_string());
+        let list = AttrList::new()
+            .add_pair(label_lines(&["x", "say \"hi\""], Align::Right))
+            .add_pair(label_lines(&["a", r"b\c"], Align::Center));
+        assert_eq!(r#"[label="x\rsay \"hi\"\r",label="a\nb\\c"]"#, list.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn graph_accessors() -> anyhow::Result<()> {
+        use crate::*;
+        let stmts = StmtList::new().add_node(Identity::id("a")?, None, None);
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::Graph)
+            .strict(true)
+            .id(Identity::id("G")?)
+            .stmts(stmts.clone())
+            .build()
+            .unwrap();
+        assert_eq!(GraphType::Graph, graph.graph_type());
+        assert!(graph.is_strict());
+        assert_eq!(Some(&Identity::id("G")?), graph.id());
+        assert_eq!(&stmts, graph.stmts());
+        let anonymous = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .stmts(StmtList::new())
+            .build()
+            .unwrap();
+        assert_eq!(None, anonymous.id());
+        assert!(!anonymous.is_strict());
+        Ok(())
+    }
+
+    #[test]
+    fn codegen_edge_ports() -> anyhow::Result<()> {
+        use crate::*;
+        let edge = Edge::head_node(Identity::id("a")?, Some(Port::id_compass(Identity::id("port")?, Compass::SouthEast)))
+            .arrow_to_node(Identity::id("b")?, None);
+        assert_eq!("a:port:se->b", edge.to_string());
+
+        let record = RecordLabel::new().port_field("f0", "left").port_field("f1", "right");
+        let list = StmtList::new()
+            .add_node(Identity::id("rec")?, None, Some(AttrList::new()
+                .add(Identity::id("shape")?, Identity::id("record")?)
+                .add(Identity::id("label")?, record.into())))
+            .add_edge(Edge::head_node(Identity::id("rec")?, Some(Port::id_compass(Identity::id("f0")?, Compass::North)))
+                .arrow_to_node(Identity::id("b")?, Some(Port::id(Identity::id("f1")?)))
+                .arrow_to_node(Identity::id("c")?, Some(Port::compass(Compass::Central))));
+        assert_eq!(r#"rec[shape=record,label="<f0> left|<f1> right"];rec:f0:n->b:f1->c:c;"#, list.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn graph_into_owned() -> anyhow::Result<()> {
+        use crate::*;
+        fn build() -> anyhow::Result<Graph<'static>> {
+            let names: Vec<String> = (0..3).map(|i| format!("n{}", i)).collect();
+            let label = String::from("a label");
+            let graph = GraphBuilder::default()
+                .graph_type(GraphType::DiGraph)
+                .strict(false)
+                .id(Identity::id(&names[0])?)
+                .stmts(StmtList::new()
+                    .add_comment(label.as_str())
+                    .add_node(Identity::id(&names[1])?, Some(Port::id(Identity::id(&names[2])?)),
+                              Some(AttrList::new().add(Identity::id("label")?, Identity::Quoted(&label))))
+                    .add_edge(Edge::head_node(Identity::id(&names[1])?, None)
+                        .arrow_to_subgraph(SubGraph::cluster(StmtList::new()
+                            .add_node(Identity::id(&names[2])?, None, None)))))
+                .build()
+                .unwrap();
+            let rendered = graph.to_string();
+            let owned = graph.into_owned();
+            drop(names);
+            drop(label);
+            assert_eq!(rendered, owned.to_string());
+            Ok(owned)
+        }
+        assert_eq!(r#"digraph n0 {/* a label */n1:n2[label="a label"];n1->{n2;};}"#, build()?.to_string());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    fn attribute_polygon() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        let list = AttrList::new().extend(polygon(5, 2)).add_pair(skew(0.5));
+        assert_eq!("[shape=polygon,sides=5,peripheries=2,skew=0.5]", list.to_string());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    fn attribute_svg_id_class() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_attr(AttrType::Graph, AttrList::new().add_pair(id("graph-root")).add_pair(class("diagram")))
+                .add_node(Identity::id("a")?, None, Some(AttrList::new()
+                    .add_pair(id("node-a"))
+                    .add_pair(classes(vec!["node", "highlight"]))
+                    .add_pair(color(Color::Red))))
+                .add_edge(Edge::head_node(Identity::id("a")?, None)
+                    .arrow_to_node(Identity::id("b")?, None)
+                    .add_attrpair(id("edge-a-b"))
+                    .add_attrpair(class("edge \"main\""))))
+            .build()
+            .unwrap();
+        assert_eq!(
+            r#"digraph {graph [id="graph-root",class="diagram"];a[id="node-a",class="node highlight",color=red];a->b[id="edge-a-b",class="edge \"main\""];}"#,
+            graph.to_string());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "mermaid")]
+    fn mermaid_export() -> anyhow::Result<()> {
+        use crate::*;
+        let chain = StmtList::new()
+            .add_equation(Identity::id("rankdir")?, Identity::id("LR")?)
+            .add_node(Identity::id("a")?, None, Some(AttrList::new().add(Identity::id("label")?, Identity::Quoted("Start"))))
+            .add_edge(Edge::head_node(Identity::id("a")?, None)
+                .arrow_to_node(Identity::id("b")?, None)
+                .arrow_to_node(Identity::id("c")?, None));
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .stmts(chain)
+            .build()
+            .unwrap();
+        assert_eq!("flowchart LR\n    a[\"Start\"]\n    a --> b\n    b --> c\n", to_mermaid(&graph));
+
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::Graph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_comment("clusters become subgraphs")
+                .add_subgraph(SubGraph::subgraph(Some(Identity::id("cluster_0")?), StmtList::new()
+                    .add_equation(Identity::id("label")?, Identity::Quoted("Group \"A\""))
+                    .add_node(Identity::Quoted("x y"), None, None)))
+                .add_edge(Edge::head_node(Identity::Quoted("x y"), None)
+                    .line_to_node(Identity::Quoted("z-1"), None)
+                    .add_attrlist(AttrList::new().add(Identity::id("label")?, Identity::Quoted("e")))))
+            .build()
+            .unwrap();
+        assert_eq!(concat!(
+            "flowchart TD\n",
+            "    %% clusters become subgraphs\n",
+            "    subgraph cluster_0[\"Group #quot;A#quot;\"]\n",
+            "        _1[\"x y\"]\n",
+            "    end\n",
+            "    _1 ---|\"e\"| _2[\"z-1\"]\n"), to_mermaid(&graph));
+
+        // made up ids skip the ids the graph already uses
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_edge(Edge::head_node(Identity::Quoted("x y"), None).arrow_to_node(Identity::id("_1")?, None))
+                .add_subgraph(SubGraph::subgraph(None, StmtList::new().add_node(Identity::id("subgraph1")?, None, None))))
+            .build()
+            .unwrap();
+        assert_eq!(concat!(
+            "flowchart TD\n",
+            "    _2[\"x y\"] --> _1\n",
+            "    subgraph subgraph2[\"subgraph2\"]\n",
+            "        subgraph1[\"subgraph1\"]\n",
+            "    end\n"), to_mermaid(&graph));
+        // a subgraph sharing its id with a node gets a made up id
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_subgraph(SubGraph::subgraph(Some(Identity::id("a")?), StmtList::new().add_node(Identity::id("a")?, None, None)))
+                .add_subgraph(SubGraph::subgraph(Some(Identity::id("s")?), StmtList::new()
+                    .add_equation(Identity::id("label")?, Identity::quoted("S"))
+                    .add_node(Identity::id("b")?, None, None)))
+                .add_edge_between(Identity::id("s")?, Identity::id("b")?, EdgeOp::Arrow))
+            .build()
+            .unwrap();
+        assert_eq!(concat!(
+            "flowchart TD\n",
+            "    subgraph subgraph1[\"a\"]\n",
+            "        a[\"a\"]\n",
+            "    end\n",
+            "    subgraph subgraph2[\"S\"]\n",
+            "        b[\"b\"]\n",
+            "    end\n",
+            "    s --> b\n"), to_mermaid(&graph));
+        Ok(())
+    }
+
+    #[test]
+    fn identity_auto() {
+        use crate::*;
+        assert_eq!(Identity::String("foo").to_string(), Identity::auto("foo").to_string());
+        assert_eq!(Identity::Quoted("foo bar").to_string(), Identity::auto("foo bar").to_string());
+        assert_eq!(Identity::String("123").to_string(), Identity::auto("123").to_string());
+        assert_eq!(Identity::String(".5").to_string(), Identity::auto(".5").to_string());
+        assert_eq!(Identity::Quoted("123abc").to_string(), Identity::auto("123abc").to_string());
+        assert_eq!(Identity::Quoted("Graph").to_string(), Identity::auto("Graph").to_string());
+        assert_eq!(Identity::Quoted("").to_string(), Identity::auto("").to_string());
+        assert_eq!("\"foo bar\"", Identity::auto("foo bar").to_string());
+    }
+
+    #[test]
+    fn identity_hash_set() -> anyhow::Result<()> {
+        use crate::*;
+        use std::collections::HashSet;
+        let ids: HashSet<Identity> = vec![
+            Identity::id("a")?,
+            Identity::id("a")?,
+            Identity::Owned("a".to_string()),
+            Identity::Quoted("a"),
+            Identity::from(1),
+            Identity::from(1),
+            Identity::from(f64::NAN),
+            Identity::from(f64::NAN),
+            Identity::from(0.0),
+            Identity::from(-0.0),
+        ].into_iter().collect();
+        assert_eq!(5, ids.len());
+        assert!(ids.contains(&Identity::String("a")));
+        assert!(ids.contains(&Identity::OwnedQuoted("a".to_string())));
+        assert!(ids.contains(&Identity::String("1")));
+        assert!(!ids.contains(&Identity::quoted("b")));
+        Ok(())
+    }
+
+    #[test]
+    fn node_attrs_lookup() -> anyhow::Result<()> {
+        use crate::*;
+        let first = AttrList::new().add(Identity::id("color")?, Identity::id("red")?);
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_node(Identity::id("a")?, None, None)
+                .add_subgraph(SubGraph::cluster(StmtList::new()
+                    .add_subgraph(SubGraph::subgraph(Some(Identity::id("cluster_x")?), StmtList::new()
+                        .add_node(Identity::id("a")?, None, Some(first.clone()))))))
+                .add_node(Identity::id("a")?, None, Some(AttrList::new().add(Identity::id("shape")?, Identity::id("box")?))))
+            .build()
+            .unwrap();
+        assert_eq!(Some(&first), graph.node_attrs(&Identity::id("a")?));
+        assert_eq!(None, graph.node_attrs(&Identity::id("b")?));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    fn attribute_emphasize() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        let edge = Edge::head_node(Identity::id("a")?, None)
+            .arrow_to_node(Identity::id("b")?, None)
+            .add_attrlist(AttrList::new().extend(emphasize(Color::Red, 2.5)));
+        assert_eq!("a->b[color=red,penwidth=2.5,style=bold]", edge.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn codegen_line_comment() -> anyhow::Result<()> {
+        use crate::*;
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .id(Identity::id("G")?)
+            .header("generated by tabbycat\ndo not edit")
+            .stmts(StmtList::new()
+                .add_line_comment("first")
+                .add_node(Identity::id("a")?, None, None)
+                .add_subgraph(SubGraph::cluster(StmtList::new()
+                    .add_line_comment("two\nlines")
+                    .add_comment("block")
+                    .add_node(Identity::id("b")?, None, None))))
+            .build()
+            .unwrap();
+        assert_eq!(
+            "/* generated by tabbycat\ndo not edit */digraph G {/* first */a;{/* two\nlines *//* block */b;};}",
+            graph.to_string());
+        assert_eq!(concat!(
+            "// generated by tabbycat\n",
+            "// do not edit\n",
+            "digraph G {\n",
+            "  // first\n",
+            "  a;\n",
+            "  {\n",
+            "    // two\n",
+            "    // lines\n",
+            "    /* block */\n",
+            "    b;\n",
+            "  };\n",
+            "}"), graph.to_string_pretty(2));
+        Ok(())
+    }
+
+    #[test]
+    fn stmtlist_defaults() -> anyhow::Result<()> {
+        use crate::*;
+        let list = StmtList::new()
+            .with_graph_defaults(AttrList::new().add(Identity::id("rankdir")?, Identity::id("LR")?))
+            .with_node_defaults(AttrList::new().add(Identity::id("shape")?, Identity::id("box")?))
+            .with_edge_defaults(AttrList::new().add(Identity::id("color")?, Identity::id("gray")?))
+            .add_node(Identity::id("a")?, None, None);
+        assert_eq!("graph [rankdir=LR];node [shape=box];edge [color=gray];a;", list.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn unicode_ids() {
+        use crate::*;
+        for id in ["naïve", "Ångström", "ελληνικά", "λ_1", "中文", "日本語_2", "e\u{301}te"].iter() {
+            assert!(Identity::id(id).is_ok(), "{} should be a valid id", id);
+            assert_eq!(Identity::String(id).to_string(), Identity::auto(id).to_string());
+        }
+        for id in ["1a", "a b", "a-b", "a\u{3000}b", "中文。", "a\u{a0}b", "—"].iter() {
+            assert!(Identity::id(id).is_err(), "{} should be rejected", id);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn json_export() -> anyhow::Result<()> {
+        use crate::*;
+        let stmts = StmtList::new()
+            .add_equation(Identity::id("rankdir")?, Identity::id("LR")?)
+            .add_node(Identity::id("a")?, None, Some(AttrList::new().add(Identity::id("label")?, Identity::Quoted("A"))))
+            .add_subgraph(SubGraph::subgraph(Some(Identity::id("cluster_0")?), StmtList::new()
+                .add_equation(Identity::id("label")?, Identity::Quoted("outer"))
+                .add_subgraph(SubGraph::cluster(StmtList::new()
+                    .add_edge_between(Identity::id("b")?, Identity::id("c")?, EdgeOp::Arrow)))))
+            .add_edge(Edge::head_node(Identity::id("a")?, None)
+                .arrow_to_node(Identity::id("b")?, None)
+                .add_attrlist(AttrList::new().add(Identity::id("color")?, Identity::id("red")?)));
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(true)
+            .id(Identity::id("G")?)
+            .stmts(stmts.clone())
+            .build()
+            .unwrap();
+        let json = to_json(&graph);
+        let mut keys: Vec<_> = json.as_object().unwrap().keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        assert_eq!(vec!["_subgraph_cnt", "directed", "edges", "name", "objects", "rankdir", "strict"], keys);
+        assert_eq!(serde_json::json!({
+            "name": "G",
+            "directed": true,
+            "strict": true,
+            "rankdir": "LR",
+            "_subgraph_cnt": 2,
+            "objects": [
+                {"_gvid": 0, "name": "cluster_0", "label": "outer", "subgraphs": [1], "nodes": [3, 4], "edges": [0]},
+                {"_gvid": 1, "name": "%2", "nodes": [3, 4], "edges": [0]},
+                {"_gvid": 2, "name": "a", "label": "A"},
+                {"_gvid": 3, "name": "b"},
+                {"_gvid": 4, "name": "c"},
+            ],
+            "edges": [
+                {"_gvid": 0, "tail": 3, "head": 4},
+                {"_gvid": 1, "tail": 2, "head": 3, "color": "red"},
+            ],
+        }), json);
+
+        let undirected = GraphBuilder::default()
+            .graph_type(GraphType::Graph)
+            .strict(false)
+            .stmts(stmts)
+            .build()
+            .unwrap();
+        let json = to_json(&undirected);
+        assert_eq!(false, json["directed"]);
+        assert_eq!("", json["name"]);
+        Ok(())
+    }
+
+    #[test]
+    fn merge_graphs() -> anyhow::Result<()> {
+        use crate::*;
+        let list = StmtList::new()
+            .add_node(Identity::id("a")?, None, None)
+            .merge(StmtList::new().add_node(Identity::id("b")?, None, None));
+        assert_eq!("a;b;", list.to_string());
+
+        let build = |graph_type, strict, stmts| GraphBuilder::default()
+            .graph_type(graph_type)
+            .strict(strict)
+            .stmts(stmts)
+            .build()
+            .unwrap();
+        let first = build(GraphType::DiGraph, false, list);
+        let second = build(GraphType::DiGraph, false, StmtList::new()
+            .add_edge_between(Identity::id("a")?, Identity::id("b")?, EdgeOp::Arrow));
+        assert_eq!("digraph {a;b;a->b;}", first.clone().merge(second)?.to_string());
+
+        let undirected = build(GraphType::Graph, false, StmtList::new());
+        assert!(first.clone().merge(undirected).is_err());
+        let strict = build(GraphType::DiGraph, true, StmtList::new());
+        assert!(first.merge(strict).is_err());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    fn attribute_arrows() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        let edge = Edge::head_node(Identity::id("a")?, None)
+            .arrow_to_node(Identity::id("b")?, None)
+            .add_attrlist(AttrList::new().extend(arrows(ArrowShape::Normal, ArrowShape::Diamond)));
+        assert_eq!("a->b[arrowhead=normal,arrowtail=diamond,dir=both]", edge.to_string());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    fn attribute_color_tuples() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        let list = AttrList::new()
+            .add_pair(fillcolor(Color::from((10, 20, 30))))
+            .add_pair(fillcolor::<Color>((10, 20, 30).into()))
+            .add_pair(color(Color::from((0, 255, 1, 2))))
+            .add_pair(bgcolor(Color::Rgba(1, 2, 3, 255)));
+        assert_eq!(r##"[fillcolor="#0a141e",fillcolor="#0a141e",color="#00ff0102",bgcolor="#010203"]"##, list.to_string());
+        let rgb: Color = (10, 20, 30).into();
+        assert_eq!(r##""#0a141e""##, Identity::from(rgb).to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn iterate_nested() -> anyhow::Result<()> {
+        use crate::*;
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_node(Identity::id("a")?, None, None)
+                .add_edge_between(Identity::id("a")?, Identity::id("b")?, EdgeOp::Arrow)
+                .add_subgraph(SubGraph::subgraph(Some(Identity::id("cluster_0")?), StmtList::new()
+                    .add_node(Identity::id("c")?, None, None)
+                    .add_subgraph(SubGraph::cluster(StmtList::new()
+                        .add_node(Identity::id("d")?, None, None)
+                        .add_edge_between(Identity::id("c")?, Identity::id("d")?, EdgeOp::Arrow)))
+                    .add_edge_between(Identity::id("b")?, Identity::id("c")?, EdgeOp::Arrow)))
+                .add_edge(Edge::head_node(Identity::id("d")?, None)
+                    .arrow_to_subgraph(SubGraph::cluster(StmtList::new()
+                        .add_edge_between(Identity::id("e")?, Identity::id("f")?, EdgeOp::Arrow)))))
+            .build()
+            .unwrap();
+        let edges: Vec<_> = graph.edges_iter().map(|edge| edge.to_string()).collect();
+        assert_eq!(vec!["a->b", "c->d", "b->c", "d->{e->f;}", "e->f"], edges);
+        let nodes: Vec<_> = graph.nodes_iter().map(|id| id.to_string()).collect();
+        assert_eq!(vec!["a", "c", "d"], nodes);
+        Ok(())
+    }
+
+    #[test]
+    fn codegen_empty_brackets() -> anyhow::Result<()> {
+        use crate::*;
+        assert_eq!("", AttrList::new().to_string());
+        assert_eq!("", AttrList::new().new_bracket().new_bracket().to_string());
+        assert!(AttrList::new().new_bracket().is_empty());
+        let mixed = AttrList::new()
+            .new_bracket()
+            .add(Identity::id("a")?, Identity::id("b")?)
+            .new_bracket()
+            .new_bracket()
+            .add(Identity::id("c")?, Identity::id("d")?)
+            .new_bracket();
+        assert!(!mixed.is_empty());
+        assert_eq!("[a=b][c=d]", mixed.to_string());
+
+        let list = StmtList::new()
+            .add_node(Identity::id("n")?, None, Some(AttrList::new().new_bracket()))
+            .add_edge(Edge::head_node(Identity::id("n")?, None)
+                .arrow_to_node(Identity::id("m")?, None)
+                .add_attrlist(AttrList::new()))
+            .add_attr(AttrType::Node, AttrList::new());
+        assert_eq!("n;n->m;node [];", list.to_string());
+        assert_eq!("n;\nn->m;\nnode [];\n", format!("{:2}", list));
+        Ok(())
+    }
+
+    #[test]
+    fn codegen_port_field() -> anyhow::Result<()> {
+        use crate::*;
+        let edge = Edge::head_node(Identity::id("a")?, Some(Port::field("f0")))
+            .arrow_to_node(Identity::id("b")?, Some(Port::field_compass("f1", Compass::South)));
+        assert_eq!("a:f0->b:f1:s", edge.to_string());
+        assert_eq!(":\"field 2\"", Port::field("field 2").to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn codegen_sorted_attrs() -> anyhow::Result<()> {
+        use crate::*;
+        let unsorted = AttrList::new()
+            .add(Identity::id("shape")?, Identity::id("box")?)
+            .add(Identity::id("color")?, Identity::id("red")?)
+            .add(Identity::id("label")?, Identity::id("x")?)
+            .add(Identity::id("color")?, Identity::id("blue")?)
+            .new_bracket()
+            .add(Identity::id("z")?, Identity::id("a")?)
+            .add(Identity::id("a")?, Identity::id("z")?);
+        assert_eq!("[color=red,color=blue,label=x,shape=box][a=z,z=a]", unsorted.clone().sorted().to_string());
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_attr(AttrType::Node, unsorted.clone())
+                .add_subgraph(SubGraph::cluster(StmtList::new()
+                    .add_node(Identity::id("n")?, None, Some(unsorted.clone()))))
+                .add_edge(Edge::head_node(Identity::id("n")?, None)
+                    .arrow_to_node(Identity::id("m")?, None)
+                    .add_attrlist(unsorted)))
+            .build()
+            .unwrap();
+        let sorted = "[color=red,color=blue,label=x,shape=box][a=z,z=a]";
+        assert_eq!(format!("digraph {{node {0};{{n{0};}};n->m{0};}}", sorted), graph.to_string_sorted());
+        assert!(graph.to_string().contains("[shape=box,color=red"));
+        Ok(())
+    }
+
+    #[test]
+    fn identity_try_from() -> anyhow::Result<()> {
+        use crate::*;
+        use std::convert::{TryFrom, TryInto};
+        assert_eq!(Identity::String("valid_id").to_string(), Identity::try_from("valid_id")?.to_string());
+        let id: Identity = "Ünïcode".try_into()?;
+        assert_eq!("Ünïcode", id.to_string());
+        match Identity::try_from("not valid") {
+            Err(Error::InvalidIdentity(data)) => assert_eq!("not valid", data),
+            other => panic!("unexpected {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn error_variants() {
+        use crate::*;
+        match Identity::id("a b") {
+            Err(Error::InvalidIdentity(data)) => assert_eq!("a b", data),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(Identity::finite(f64::INFINITY), Err(Error::NonFiniteNumber(n)) if n.is_infinite()));
+        let build = |graph_type| GraphBuilder::default()
+            .graph_type(graph_type)
+            .strict(false)
+            .stmts(StmtList::new())
+            .build()
+            .unwrap();
+        let error = build(GraphType::DiGraph).merge(build(GraphType::Graph)).unwrap_err();
+        assert_eq!(Error::IncompatibleGraphs {
+            graph_type: GraphType::DiGraph,
+            strict: false,
+            other_graph_type: GraphType::Graph,
+            other_strict: false,
+        }, error);
+        assert_eq!("cannot merge a Graph into a DiGraph", error.to_string());
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    fn attribute_rank_constraint() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        let edge = Edge::head_node(Identity::id("a")?, None)
+            .arrow_to_node(Identity::id("b")?, None)
+            .add_attrlist(AttrList::new().extend(rank_constraint(3.0, false)));
+        assert_eq!("a->b[weight=3,constraint=false]", edge.to_string());
+        assert_eq!("[weight=0.5,constraint=true]", AttrList::new().extend(rank_constraint(0.5, true)).to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn codegen_subgraph_spacing() -> anyhow::Result<()> {
+        use crate::*;
+        let body = || -> anyhow::Result<StmtList> { Ok(StmtList::new().add_node(Identity::id("a")?, None, None)) };
+        let named = SubGraph::subgraph(Some(Identity::id("s")?), body()?);
+        let anonymous = SubGraph::subgraph(None, body()?);
+        let cluster = SubGraph::cluster(body()?);
+        assert_eq!("subgraph s {a;}", named.to_string());
+        assert_eq!("subgraph {a;}", anonymous.to_string());
+        assert_eq!("{a;}", cluster.to_string());
+        assert_eq!("subgraph s {\n  a;\n}", format!("{:2}", named));
+        assert_eq!("subgraph {\n  a;\n}", format!("{:2}", anonymous));
+        assert_eq!("subgraph {}", SubGraph::subgraph(None, StmtList::new()).to_string());
+        assert_eq!("subgraph s {}", format!("{:2}", SubGraph::subgraph(Some(Identity::id("s")?), StmtList::new())));
+        Ok(())
+    }
+
+    #[test]
+    fn identity_as_str() -> anyhow::Result<()> {
+        use crate::*;
+        assert_eq!(Some("a"), Identity::id("a")?.as_str());
+        assert_eq!(Some("a b"), Identity::quoted("a b").as_str());
+        assert_eq!(Some("x"), Identity::Owned("x".to_string()).as_str());
+        assert_eq!(Some("x\\l"), Identity::EscString("x\\l".to_string()).as_str());
+        assert_eq!(None, Identity::from(42).as_str());
+        assert_eq!(None, Identity::from(1.5).as_str());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    fn graph_builder_shortcuts() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .id(Identity::id("G")?)
+            .bgcolor(Color::White)
+            .build()
+            .unwrap();
+        assert_eq!(Some(&Stmt::Attr(AttrType::Graph, AttrList::new().add_pair(bgcolor(Color::White)))), graph.stmts().0.first());
+        assert_eq!("digraph G {graph [bgcolor=white];}", graph.to_string());
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::Graph)
+            .strict(false)
+            .stmts(StmtList::new().add_node(Identity::id("a")?, None, None))
+            .label("title")
+            .splines(Splines::Ortho)
+            .build()
+            .unwrap();
+        assert_eq!("graph {graph [label=\"title\",splines=ortho];a;}", graph.to_string());
+        let before = GraphBuilder::default()
+            .graph_type(GraphType::Graph)
+            .strict(false)
+            .label("title")
+            .splines(Splines::Ortho)
+            .stmts(StmtList::new().add_node(Identity::id("a")?, None, None))
+            .build()
+            .unwrap();
+        assert_eq!(graph, before);
+        Ok(())
+    }
+
+    #[test]
+    fn codegen_quoted_ids() -> anyhow::Result<()> {
+        use crate::*;
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .id(Identity::quoted("my \"big\" graph"))
+            .stmts(StmtList::new()
+                .add_subgraph(SubGraph::subgraph(Some(Identity::OwnedQuoted("sub \"a\"".to_string())), StmtList::new())))
+            .build()
+            .unwrap();
+        assert_eq!(r#"digraph "my \"big\" graph" {subgraph "sub \"a\"" {};}"#, graph.to_string());
+        assert_eq!(r#""a\\b""#, Identity::quoted(r"a\b").to_string());
+        assert_eq!(r#""two\nlines""#, Identity::quoted("two\nlines").to_string());
+        assert_eq!("\"tab\tand e\u{301}\"", Identity::quoted("tab\tand e\u{301}").to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn codegen_edge_path() -> anyhow::Result<()> {
+        use crate::*;
+        let ids = |names: &[&'static str]| names.iter().map(|name| Identity::id(name).unwrap()).collect::<Vec<_>>();
+        assert_eq!("a->b->c->d", Edge::arrow_path(Identity::id("a")?, ids(&["b", "c", "d"])).to_string());
+        assert_eq!("a--b", Edge::line_path(Identity::id("a")?, ids(&["b"])).to_string());
+        assert_eq!("a", Edge::line_path(Identity::id("a")?, ids(&[])).to_string());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    fn validate_cluster_edges() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        let stmts = StmtList::new()
+            .add_attr(AttrType::Edge, AttrList::new().add_pair(ltail("cluster_a")))
+            .add_edge(Edge::head_node(Identity::id("a")?, None)
+                .arrow_to_node(Identity::id("b")?, None)
+                .add_attrlist(AttrList::new().add_pair(lhead("cluster_b"))));
+        let graph = |stmts: StmtList<'static>| GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .stmts(stmts)
+            .build()
+            .unwrap();
+        let errors = graph(stmts.clone()).validate().unwrap_err();
+        assert_eq!(2, errors.len());
+        assert_eq!("`lhead`/`ltail` without `compound=true` on the graph: edge [ltail=\"cluster_a\"]", errors[0].to_string());
+        match &errors[1] {
+            ValidationError::CompoundRequired { stmt } => assert_eq!("a->b[lhead=\"cluster_b\"]", stmt),
+            error => panic!("unexpected error: {:?}", error),
+        }
+        let enabled = StmtList::new()
+            .add_attr(AttrType::Graph, AttrList::new().add_pair(enable_cluster_edges()))
+            .merge(stmts.clone());
+        assert!(graph(enabled).validate().is_ok());
+        let equation = StmtList::new()
+            .add_equation(Identity::id("compound")?, Identity::id("yes")?)
+            .merge(stmts);
+        assert!(graph(equation).validate().is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn visitor_uppercase_ids() -> anyhow::Result<()> {
+        use crate::*;
+        struct Uppercase;
+        impl<'a> GraphVisitor<'a> for Uppercase {
+            fn visit_node_id(&mut self, id: &mut Identity<'a>) {
+                if let Some(text) = id.as_str() {
+                    let upper = text.to_uppercase();
+                    *id = match id {
+                        Identity::Quoted(_) | Identity::OwnedQuoted(_) => Identity::OwnedQuoted(upper),
+                        Identity::EscString(_) => Identity::EscString(upper),
+                        _ => Identity::Owned(upper),
+                    };
+                }
+            }
+        }
+        let mut graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .id(Identity::id("g")?)
+            .stmts(StmtList::new()
+                .add_node(Identity::id("a")?, None, None)
+                .add_node(Identity::from(1), None, None)
+                .add_subgraph(SubGraph::subgraph(Some(Identity::id("s")?), StmtList::new()
+                    .add_edge(Edge::head_node(Identity::quoted("b c"), None)
+                        .arrow_to_subgraph(SubGraph::cluster(StmtList::new().add_node(Identity::id("d")?, None, None)))))))
+            .build()
+            .unwrap();
+        graph.accept(&mut Uppercase);
+        assert_eq!("digraph g {A;1;subgraph s {\"B C\"->{D;};};}", graph.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn stmtlist_add_node_attribute() -> anyhow::Result<()> {
+        use crate::*;
+        let color = || -> anyhow::Result<AttrPair> { Ok((Identity::id("color")?, Identity::id("red")?)) };
+        let stmts = StmtList::new()
+            .add_node(Identity::id("a")?, None, Some(AttrList::new().add(Identity::id("shape")?, Identity::id("box")?)))
+            .add_subgraph(SubGraph::cluster(StmtList::new().add_node(Identity::id("b")?, None, None)))
+            .add_edge(Edge::head_node(Identity::id("a")?, None).arrow_to_node(Identity::id("c")?, None))
+            .add_node_attribute(Identity::id("a")?, color()?)
+            .add_node_attribute(Identity::id("b")?, color()?)
+            .add_node_attribute(Identity::id("c")?, color()?);
+        assert_eq!(Some(&AttrList::new().add(Identity::id("shape")?, Identity::id("box")?).add_pair(color()?)),
+                   match &stmts.0[0] {
+                       Stmt::Node { attr, .. } => attr.as_ref(),
+                       _ => None,
+                   });
+        assert_eq!("a[shape=box,color=red];{b[color=red];};a->c;c[color=red];", stmts.to_string());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    fn attribute_no_overlap() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        assert_eq!("[overlap=false,sep=\"+4\",esep=\"+4\"]", AttrList::new().extend(no_overlap(4.0)?).to_string());
+        assert_eq!("[overlap=false,sep=\"+0.5\",esep=\"+0.5\"]", AttrList::new().extend(no_overlap(0.5)?).to_string());
+        assert_eq!("[overlap=false,sep=\"+0\",esep=\"+0\"]", AttrList::new().extend(no_overlap(0.0)?).to_string());
+        assert_eq!(Err(Error::NegativeNumber(-1.0)), no_overlap(-1.0));
+        assert!(matches!(no_overlap(f64::NAN), Err(Error::NonFiniteNumber(_))));
+        assert_eq!(Err(Error::NonFiniteNumber(f64::INFINITY)), no_overlap(f64::INFINITY));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    fn attribute_non_negative_counts() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        let list = AttrList::new()
+            .add_pair(peripheries(0))
+            .add_pair(minlen(u32::MAX))
+            .extend(polygon(3, 0));
+        assert_eq!("[peripheries=0,minlen=4294967295,shape=polygon,sides=3,peripheries=0]", list.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn stmtlist_add_adjacency() -> anyhow::Result<()> {
+        use crate::*;
+        let adjacency = vec![
+            (Identity::from(0), vec![Identity::from(1), Identity::from(2)]),
+            (Identity::from(1), vec![]),
+            (Identity::from(2), vec![Identity::from(0)]),
+        ];
+        let stmts = StmtList::new()
+            .add_node(Identity::id("x")?, None, None)
+            .add_adjacency(adjacency, EdgeOp::Line);
+        assert_eq!(3, stmts.0.iter().filter(|stmt| matches!(stmt, Stmt::Edge(_))).count());
+        assert_eq!("x;0--1;0--2;2--0;", stmts.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn graph_write_fmt_into() -> anyhow::Result<()> {
+        use crate::*;
+        let names: Vec<String> = (0..10_000).map(|i| format!("n{}", i)).collect();
+        let stmts = names.windows(2)
+            .fold(StmtList::new().add_nodes(names.iter().map(|name| Identity::id(name).unwrap())), |stmts, pair| {
+                stmts.add_edge_between(Identity::id(&pair[0]).unwrap(), Identity::id(&pair[1]).unwrap(), EdgeOp::Arrow)
+            });
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .id(Identity::id("G")?)
+            .stmts(stmts)
+            .build()
+            .unwrap();
+        let mut buf = String::new();
+        graph.write_fmt_into(&mut buf);
+        assert_eq!(graph.to_string(), buf);
+        // the estimate covers the whole output, counted without any buffer in between
+        struct Counter(usize);
+        impl std::fmt::Write for Counter {
+            fn write_str(&mut self, s: &str) -> std::fmt::Result {
+                self.0 += s.len();
+                Ok(())
+            }
+        }
+        let mut counter = Counter(0);
+        std::fmt::write(&mut counter, format_args!("{}", graph))?;
+        assert_eq!(buf.len(), counter.0);
+        assert!(graph.estimated_len() >= counter.0);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    fn attribute_layers() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        assert_eq!("[layers=\"a:b:c\"]", AttrList::new().add_pair(layers(&["a", "b", "c"])).to_string());
+        assert_eq!("[layersep=\"|\",layers=\"front|back\"]", AttrList::new().extend(layers_separated(&["front", "back"], '|')).to_string());
+        assert_eq!("[layer=\"a:b\",layerselect=\"b\"]", AttrList::new().add_pair(layer("a:b")).add_pair(layerselect("b")).to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn graph_diff() -> anyhow::Result<()> {
+        use crate::*;
+        let graph = |stmts| GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .stmts(stmts)
+            .build()
+            .unwrap();
+        let color = |value| -> anyhow::Result<Option<AttrList>> { Ok(Some(AttrList::new().add(Identity::id("color")?, Identity::id(value)?))) };
+        let old = graph(StmtList::new()
+            .add_node(Identity::id("a")?, None, color("red")?)
+            .add_node(Identity::id("b")?, None, None)
+            .add_node(Identity::id("c")?, None, None)
+            .add_edge_between(Identity::id("a")?, Identity::id("b")?, EdgeOp::Arrow));
+        let new = graph(StmtList::new()
+            .add_node(Identity::id("a")?, None, color("blue")?)
+            .add_node(Identity::id("c")?, None, None)
+            .add_edge_between(Identity::id("a")?, Identity::id("b")?, EdgeOp::Arrow)
+            .add_edge_between(Identity::id("a")?, Identity::id("c")?, EdgeOp::Arrow));
+        let diff = old.diff(&new);
+        let strings = |stmts: &[&Stmt]| stmts.iter().map(|stmt| stmt.to_string()).collect::<Vec<_>>();
+        assert_eq!(vec!["a->c"], strings(&diff.added));
+        assert_eq!(vec!["b"], strings(&diff.removed));
+        assert_eq!(1, diff.changed.len());
+        assert_eq!(("a[color=red]".to_string(), "a[color=blue]".to_string()),
+                   (diff.changed[0].0.to_string(), diff.changed[0].1.to_string()));
+        assert!(new.diff(&new).is_empty());
+        let owned = new.clone().into_owned();
+        assert!(new.diff(&owned).is_empty());
+        let quoted = graph(StmtList::new()
+            .add_node(Identity::id("a")?, None, color("blue")?)
+            .add_node(Identity::quoted("c"), None, None)
+            .add_edge_between(Identity::id("a")?, Identity::quoted("b"), EdgeOp::Arrow)
+            .add_edge_between(Identity::quoted("a"), Identity::id("c")?, EdgeOp::Arrow));
+        assert!(new.diff(&quoted).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn codegen_header_spacing() -> anyhow::Result<()> {
+        use crate::*;
+        let graph = |graph_type, strict, id: Option<Identity<'static>>| {
+            let builder = GraphBuilder::default()
+                .graph_type(graph_type)
+                .strict(strict)
+                .stmts(StmtList::new());
+            match id {
+                Some(id) => builder.id(id),
+                None => builder,
+            }.build().unwrap()
+        };
+        let cases = vec![
+            (graph(GraphType::DiGraph, true, Some(Identity::id("G")?)), "strict digraph G {}"),
+            (graph(GraphType::DiGraph, false, None), "digraph {}"),
+            (graph(GraphType::Graph, true, None), "strict graph {}"),
+            (graph(GraphType::Graph, false, Some(Identity::id("G")?)), "graph G {}"),
+        ];
+        for (graph, expected) in cases {
+            assert_eq!(expected, graph.to_string());
+            assert_eq!(expected, graph.to_string_pretty(2));
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    fn attribute_spacing() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        assert_eq!("[nodesep=0.1,ranksep=0.2]", AttrList::new().extend(spacing(0.1, 0.2)).to_string());
+        assert_eq!("[nodesep=1,ranksep=\"2 equally\"]", AttrList::new().extend(spacing_equally(1.0, 2.0)).to_string());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    fn attribute_ranksep() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        let render = |value: Ranksep| -> anyhow::Result<String> { Ok(AttrList::new().add_pair(rank_separation(value)?).to_string()) };
+        assert_eq!("[ranksep=0.75]", render(0.75.into())?);
+        assert_eq!("[ranksep=2]", render(Ranksep::Value(2.0))?);
+        assert_eq!("[ranksep=\"1.5 equally\"]", render(Ranksep::Equally(1.5))?);
+        assert_eq!("[ranksep=\"0.5:1:2\"]", render(Ranksep::List(vec![0.5, 1.0, 2.0]))?);
+        assert_eq!("[ranksep=0.75]", AttrList::new().add_pair(ranksep(0.75)).to_string());
+        assert_eq!(Err(Error::EmptyList), rank_separation(Ranksep::List(vec![])));
+        assert_eq!(Err(Error::NegativeNumber(-1.0)), rank_separation(Ranksep::Value(-1.0)));
+        assert_eq!(Err(Error::NegativeNumber(-0.5)), rank_separation(Ranksep::Equally(-0.5)));
+        assert_eq!(Err(Error::NonFiniteNumber(f64::INFINITY)), rank_separation(Ranksep::List(vec![1.0, f64::INFINITY])));
+        assert!(matches!(rank_separation(f64::NAN.into()), Err(Error::NonFiniteNumber(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn validate_record_labels() -> anyhow::Result<()> {
+        use crate::*;
+        let attrs = |shape: Option<&'static str>, label: Identity<'static>| -> anyhow::Result<Option<AttrList<'static>>> {
+            let list = match shape {
+                Some(shape) => AttrList::new().add(Identity::id("shape")?, Identity::id(shape)?),
+                None => AttrList::new(),
+            };
+            Ok(Some(list.add(Identity::id("label")?, label)))
+        };
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::Graph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_node(Identity::id("a")?, None, attrs(Some("record"), Identity::quoted("plain"))?)
+                .add_node(Identity::id("b")?, None, attrs(Some("record"), RecordLabel::new().field("x").field("y").into())?)
+                .add_node(Identity::id("c")?, None, attrs(None, Identity::quoted("plain"))?)
+                .add_subgraph(SubGraph::cluster(StmtList::new()
+                    .add_attr(AttrType::Node, AttrList::new().add(Identity::id("shape")?, Identity::id("Mrecord")?))
+                    .add_node(Identity::id("d")?, None, attrs(None, Identity::quoted(r"a\|b"))?)
+                    .add_node(Identity::id("e")?, None, attrs(None, Identity::quoted("<p> port"))?)
+                    .add_node(Identity::id("g")?, None, attrs(None, Identity::EscString(r"a\|b".to_string()))?)))
+                .add_node(Identity::id("f")?, None, attrs(None, Identity::quoted("plain"))?))
+            .build()
+            .unwrap();
+        let errors = graph.validate().unwrap_err();
+        assert_eq!(vec![
+            "record shaped node with a plain label: a[shape=record,label=\"plain\"]",
+            "record shaped node with a plain label: g[label=\"a\\|b\"]",
+        ], errors.iter().map(|error| error.to_string()).collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[test]
+    fn graph_from_edges() -> anyhow::Result<()> {
+        use crate::*;
+        let graph = Graph::from_edges(GraphType::DiGraph, vec![
+            (Identity::id("a")?, Identity::id("b")?),
+            (Identity::id("b")?, Identity::id("c")?),
+            (Identity::from(1), Identity::quoted("a b")),
+        ]);
+        assert_eq!(3, graph.edges_iter().count());
+        assert_eq!(0, graph.nodes_iter().count());
+        assert_eq!("digraph {a->b;b->c;1->\"a b\";}", graph.to_string());
+        assert!(graph.validate().is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn identity_auto_numeric_strings() -> anyhow::Result<()> {
+        use crate::*;
+        assert_eq!(Identity::Quoted("0123").to_string(), Identity::auto("0123").to_string());
+        assert_eq!(Identity::Quoted("-007").to_string(), Identity::auto("-007").to_string());
+        assert_eq!(Identity::Quoted("1.2.3").to_string(), Identity::auto("1.2.3").to_string());
+        assert_eq!(Identity::Quoted("1e10").to_string(), Identity::auto("1e10").to_string());
+        assert_eq!("\"1e10\"", Identity::auto("1e10").to_string());
+        for numeral in &["0", "-0", "0.5", "10", "100.", ".25", "-0.75"] {
+            assert_eq!(Identity::String(numeral).to_string(), Identity::auto(numeral).to_string());
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    fn attribute_link() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        let attrs = AttrList::new().extend(link("https://example.com/search?q=a+b&page=2#\"top\"", "Search \"a b\""));
+        assert_eq!(r#"[URL="https://example.com/search?q=a+b&page=2#\"top\"",tooltip="Search \"a b\"",target="_blank"]"#, attrs.to_string());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    fn attribute_stroke() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        assert_eq!("[color=red,penwidth=1.5]", AttrList::new().extend(stroke(Color::Red, 1.5)?).to_string());
+        assert_eq!("[color=red,penwidth=0]", AttrList::new().extend(stroke(Color::Red, 0.0)?).to_string());
+        assert_eq!(Err(Error::NegativeNumber(-0.5)), stroke(Color::Red, -0.5));
+        assert_eq!("negative number -0.5", stroke(Color::Red, -0.5).unwrap_err().to_string());
+        assert!(matches!(stroke(Color::Red, f64::NAN), Err(Error::NonFiniteNumber(n)) if n.is_nan()));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    #[allow(deprecated)]
+    fn attribute_external_label() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        let stmts = StmtList::new()
+            .add_node(Identity::id("a")?, None, Some(AttrList::new().extend(external_label("a \"quoted\" note"))));
+        assert_eq!(r#"a[xlabel="a \"quoted\" note",forcelabels=true];"#, stmts.to_string());
+        assert_eq!(forcelabels(false), forcelables(false));
+        Ok(())
+    }
+
+    #[test]
+    fn list_lengths() -> anyhow::Result<()> {
+        use crate::*;
+        assert!(StmtList::new().is_empty());
+        assert_eq!(0, StmtList::new().len());
+        assert_eq!(StmtList::new(), StmtList::default());
+        let stmts = StmtList::new()
+            .add_node(Identity::id("a")?, None, None)
+            .add_subgraph(SubGraph::cluster(StmtList::new().add_node(Identity::id("b")?, None, None)));
+        assert!(!stmts.is_empty());
+        assert_eq!(2, stmts.len());
+
+        assert!(AttrList::new().is_empty());
+        assert_eq!((0, 0), (AttrList::new().len(), AttrList::new().group_count()));
+        let attrs = AttrList::new()
+            .add(Identity::id("a")?, Identity::from(1))
+            .add(Identity::id("b")?, Identity::from(2))
+            .new_bracket()
+            .new_bracket()
+            .add(Identity::id("c")?, Identity::from(3));
+        assert!(!attrs.is_empty());
+        assert_eq!(3, attrs.len());
+        assert_eq!(2, attrs.group_count());
+        assert_eq!(0, AttrList::new().new_bracket().group_count());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    fn subgraph_styled_cluster() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        let sub = SubGraph::styled_cluster(Identity::id("cluster_0")?, Color::White, "a \"b\"", StmtList::new()
+            .add_edge_between(Identity::id("a")?, Identity::id("b")?, EdgeOp::Arrow));
+        assert_eq!(Some(&Stmt::Attr(AttrType::Graph, AttrList::new()
+            .add_pair(style(Style::Filled))
+            .add_pair(fillcolor(Color::White))
+            .add_pair(label("a \"b\"")))), sub.stmts().0.first());
+        assert_eq!(r#"subgraph cluster_0 {graph [style=filled,fillcolor=white,label="a \"b\""];a->b;}"#, sub.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn codegen_label_placeholders() -> anyhow::Result<()> {
+        use crate::*;
+        let label = |text| AttrList::new().add(Identity::String("label"), Identity::quoted(text)).to_string();
+        assert_eq!(r#"[label="\\N in \\G"]"#, label(r"\N in \G"));
+        assert_eq!(r#"[label="a\\b"]"#, label(r"a\b"));
+        assert_eq!(r#"[label="double \\\\N"]"#, label(r"double \\N"));
+        assert_eq!(r#"[label="trailing\\"]"#, label("trailing\\"));
+        assert_eq!(r#"[label="\\\"quoted\\\""]"#, label(r#"\"quoted\""#));
+        assert_eq!(r#""\\N""#, Identity::OwnedQuoted(r"\N".to_string()).to_string());
+        let template = |text| AttrList::new().add(Identity::String("label"), Identity::label_template(text)).to_string();
+        assert_eq!(r#"[label="\N in \G"]"#, template(r"\N in \G"));
+        assert_eq!(r#"[label="\E \H \T \L"]"#, template(r"\E \H \T \L"));
+        assert_eq!(r#"[label="a\\b"]"#, template(r"a\b"));
+        assert_eq!(r#"[label="literal \\N"]"#, template(r"literal \\N"));
+        assert_eq!(r#"[label="trailing\\"]"#, template("trailing\\"));
+        assert_eq!(r#"[label="\N says \"hi\"\n"]"#, template("\\N says \"hi\"\n"));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    fn attribute_size_ratio() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        let render = |pair| AttrList::new().add_pair(pair).to_string();
+        assert_eq!("[size=\"8,6!\"]", render(size(Size::Rect { width: 8.0, height: 6.0, fill: true })));
+        assert_eq!("[size=\"8.5,11\"]", render(size(Size::Rect { width: 8.5, height: 11.0, fill: false })));
+        assert_eq!("[size=\"5!\"]", render(size(Size::Square { size: 5.0, fill: true })));
+        assert_eq!("[size=5]", render(size(5.0)));
+        assert_eq!("[ratio=fill]", render(ratio(Ratio::Fill)));
+        assert_eq!("[ratio=compress]", render(ratio(Ratio::Compress)));
+        assert_eq!("[ratio=expand]", render(ratio(Ratio::Expand)));
+        assert_eq!("[ratio=auto]", render(ratio(Ratio::Auto)));
+        assert_eq!("[ratio=0.5]", render(ratio(0.5)));
+        Ok(())
+    }
+
+    #[test]
+    fn stmtlist_edit_in_place() -> anyhow::Result<()> {
+        use crate::*;
+        let mut graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_node(Identity::id("a")?, None, None)
+                .add_edge_between(Identity::id("a")?, Identity::id("b")?, EdgeOp::Arrow))
+            .build()
+            .unwrap();
+        if let Some(Stmt::Node { id, .. }) = graph.stmts_mut().get_mut(0) {
+            *id = Identity::id("c")?;
+        }
+        assert!(graph.stmts_mut().get_mut(2).is_none());
+        for stmt in graph.stmts_mut().iter_mut() {
+            if let Stmt::Edge(edge) = stmt {
+                *edge = Edge::head_node(Identity::id("c")?, None).arrow_to_node(Identity::id("b")?, None);
+            }
+        }
+        assert_eq!("digraph {c;c->b;}", graph.to_string());
+        assert_eq!(Some(&Stmt::Node { id: Identity::id("c")?, port: None, attr: None }), graph.stmts().get(0));
+        assert_eq!(2, graph.stmts().iter().count());
+        Ok(())
+    }
+
+    #[test]
+    fn validate_string_identities() -> anyhow::Result<()> {
+        use crate::*;
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .id(Identity::String("my graph"))
+            .stmts(StmtList::new()
+                .add_node(Identity::String("has space"), None, None)
+                .add_node(Identity::String("ok_id"), None, Some(AttrList::new()
+                    .add(Identity::String("label"), Identity::String("<<b>bold</b>>"))
+                    .add(Identity::String("width"), Identity::String("-1.5"))))
+                .add_subgraph(SubGraph::subgraph(Some(Identity::Owned("a-b".to_string())), StmtList::new()
+                    .add_edge(Edge::head_node(Identity::String("x"), None)
+                        .arrow_to_node(Identity::String("y;z"), None)))))
+            .build()
+            .unwrap();
+        let errors = graph.validate().unwrap_err();
+        assert_eq!(vec![
+            "unquoted identity is not a valid id: my graph",
+            "unquoted identity is not a valid id: has space",
+            "unquoted identity is not a valid id: a-b",
+            "unquoted identity is not a valid id: y;z",
+        ], errors.iter().map(|error| error.to_string()).collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[test]
+    fn validate_keyword_identities() -> anyhow::Result<()> {
+        use crate::*;
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::Graph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_node(Identity::String("Node"), None, None)
+                .add_node(Identity::quoted("node"), None, None)
+                .add_edge(Edge::head_node(Identity::String("edges"), None)
+                    .line_to_node(Identity::Owned("subgraph".to_string()), None)))
+            .build()
+            .unwrap();
+        let errors = graph.validate().unwrap_err();
+        assert_eq!(vec![
+            "unquoted identity is not a valid id: Node",
+            "unquoted identity is not a valid id: subgraph",
+        ], errors.iter().map(|error| error.to_string()).collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[test]
+    fn validate_html_identities() -> anyhow::Result<()> {
+        use crate::*;
+        let label = |text| Some(AttrList::new().add(Identity::String("label"), Identity::String(text)));
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::Graph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_node(Identity::String("a"), None, label("<<b>bold</b>>"))
+                .add_node(Identity::String("b"), None, label("<<b>bold</b>"))
+                .add_node(Identity::String("c"), None, label("<a>b<c>"))
+                .add_node(Identity::String("d"), None, label("<a>>")))
+            .build()
+            .unwrap();
+        let errors = graph.validate().unwrap_err();
+        assert_eq!(vec![
+            "unquoted identity is not a valid id: <<b>bold</b>",
+            "unquoted identity is not a valid id: <a>b<c>",
+            "unquoted identity is not a valid id: <a>>",
+        ], errors.iter().map(|error| error.to_string()).collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    fn attribute_layout_presets() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        assert_eq!("[mindist=0.5]", AttrList::new().extend(circo_compact()).to_string());
+        assert_eq!("[K=0.6,repulsiveforce=2]", AttrList::new().extend(fdp_spacious()).to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn codegen_edge_ports_convenience() -> anyhow::Result<()> {
+        use crate::*;
+        let stmts = StmtList::new()
+            .add_edge_between_ports(
+                (Identity::id("a")?, Some(Port::field("p1"))),
+                (Identity::id("b")?, Some(Port::field("p2"))),
+                EdgeOp::Arrow)
+            .add_edge(Edge::arrow_path_ports(
+                (Identity::id("a")?, Some(Port::field("p1"))),
+                vec![(Identity::id("b")?, None), (Identity::id("c")?, Some(Port::field_compass("p3", Compass::North)))]))
+            .add_edge(Edge::line_path_ports((Identity::id("a")?, None), vec![(Identity::id("b")?, Some(Port::compass(Compass::South)))]));
+        assert_eq!("a:p1->b:p2;a:p1->b->c:p3:n;a--b:s;", stmts.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn graph_canonicalize() -> anyhow::Result<()> {
+        use crate::*;
+        let graph = |attr: AttrList<'static>| GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_attr(AttrType::Node, attr.clone())
+                .add_node(Identity::id("a")?, None, Some(attr.clone()))
+                .add_subgraph(SubGraph::cluster(StmtList::new()
+                    .add_edge(Edge::head_node(Identity::id("a")?, None)
+                        .arrow_to_node(Identity::id("b")?, None)
+                        .add_attrlist(attr)))))
+            .build()
+            .map_err(anyhow::Error::msg);
+        let key = |name| Identity::id(name).unwrap();
+        let grouped = graph(AttrList::new()
+            .add(key("color"), key("red")).new_bracket()
+            .add(key("shape"), key("box")).new_bracket())?;
+        let single = graph(AttrList::new()
+            .add(key("color"), key("red"))
+            .add(key("shape"), key("box")))?;
+        assert_ne!(grouped.to_string(), single.to_string());
+        assert_eq!(single.to_string(), grouped.canonicalize().to_string());
+        assert_eq!("digraph {node [color=red,shape=box];a[color=red,shape=box];{a->b[color=red,shape=box];};}",
+                   single.clone().canonicalize().to_string());
+        let empty = AttrList::new().new_bracket().new_bracket().flattened();
+        assert_eq!((0, empty.to_string()), (empty.group_count(), AttrList::new().to_string()));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "attributes")]
+    fn attribute_paging() -> anyhow::Result<()> {
+        use crate::*;
+        use crate::attributes::*;
+        assert_eq!("[pagedir=TL]", AttrList::new().add_pair(pagedir(PageDir::TL)).to_string());
+        assert_eq!("[page=\"8.5,11\"]", AttrList::new().add_pair(page(PageSize::Rect { width: 8.5, height: 11.0 })).to_string());
+        assert_eq!("[page=8.5]", AttrList::new().add_pair(page(8.5)).to_string());
+        assert_eq!("[page=5]", AttrList::new().add_pair(page(PageSize::Square(5.0))).to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn attrlist_inner() -> anyhow::Result<()> {
+        use crate::*;
+        let list = AttrList::new()
+            .add(Identity::id("shape")?, Identity::id("box")?)
+            .new_bracket()
+            .new_bracket()
+            .add(Identity::id("width")?, Identity::Double(1.5));
+        assert_eq!("shape=box,width=1.5", list.to_inner_string());
+        assert_eq!("", AttrList::new().to_inner_string());
+        struct Pretty<'l>(&'l AttrList<'l>);
+        impl std::fmt::Display for Pretty<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                self.0.fmt_inner(f)
+            }
+        }
+        assert_eq!("shape=box, width=1.5", format!("{:#4}", Pretty(&list)));
+        Ok(())
+    }
+
+    #[test]
+    fn validate_duplicate_subgraph_ids() -> anyhow::Result<()> {
+        use crate::*;
+        let cluster = |id| -> anyhow::Result<SubGraph<'static>> {
+            Ok(SubGraph::subgraph(Some(Identity::id(id)?), StmtList::new().add_node(Identity::id("a")?, None, None)))
+        };
+        let graph = GraphBuilder::default()
+            .graph_type(GraphType::DiGraph)
+            .strict(false)
+            .stmts(StmtList::new()
+                .add_subgraph(cluster("cluster_x")?)
+                .add_subgraph(cluster("cluster_y")?)
+                .add_subgraph(SubGraph::subgraph(None, StmtList::new()
+                    .add_subgraph(cluster("cluster_x")?)))
+                .add_edge(Edge::head_subgraph(cluster("cluster_x")?)
+                    .arrow_to_subgraph(cluster("cluster_y")?))
+                .add_subgraph(cluster("a")?)
+                .add_subgraph(SubGraph::subgraph(Some(Identity::quoted("a")), StmtList::new())))
+            .build()
+            .unwrap();
+        let errors = graph.validate().unwrap_err();
+        assert_eq!(vec![
+            "subgraph id used more than once: cluster_x",
+            "subgraph id used more than once: cluster_y",
+            "subgraph id used more than once: a",
+        ], errors.iter().map(|error| error.to_string()).collect::<Vec<_>>());
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+//! The `dot!` macro, a shorthand for building small graphs with a DOT-like syntax.
+use crate::Identity;
+
+/// Converts the literals accepted by `dot!` into identities: strings become quoted strings,
+/// numbers and booleans use the corresponding `From` implementation.
+#[doc(hidden)]
+pub trait DotLiteral<'a> {
+    fn into_identity(self) -> Identity<'a>;
+}
+
+impl<'a> DotLiteral<'a> for &'a str {
+    fn into_identity(self) -> Identity<'a> {
+        Identity::Quoted(self)
+    }
+}
+
+macro_rules! dot_literal_from {
+    ($($t:ty),*) => {
+        $(
+            impl<'a> DotLiteral<'a> for $t {
+                fn into_identity(self) -> Identity<'a> {
+                    Identity::from(self)
+                }
+            }
+        )*
+    };
+}
+
+dot_literal_from!(bool, isize, usize, i8, u8, i16, u16, i32, u32, i64, u64, i128, u128, f32, f64);
+
+/// Whether an identifier used as an id is a DOT keyword, checked at compile time by `dot!`.
+#[doc(hidden)]
+pub const fn is_keyword(text: &str) -> bool {
+    crate::graph::is_keyword(text)
+}
+
+/// Build a `Graph` with a DOT-like syntax:
+/// ```
+/// use tabbycat::dot;
+/// let graph = dot!(strict digraph G {
+///     rankdir = LR;
+///     node [shape = box];
+///     a [label = "start"];
+///     a -> b -> c [color = red];
+///     c -> a;
+/// });
+/// assert_eq!(
+///     "strict digraph G {rankdir=LR;node [shape=box];a[label=\"start\"];a->b->c[color=red];c->a;}",
+///     graph.to_string());
+/// ```
+/// The header is `graph` or `digraph`, optionally preceded by `strict` and followed by an id.
+/// Each statement ends with `;` and is one of:
+/// - a node `a` or `a [k = v, ...]`,
+/// - an edge chain `a -> b -> c` (or `a -- b` in undirected graphs), optionally followed by `[k = v, ...]`,
+/// - default attributes `graph [...]`, `node [...]` or `edge [...]`,
+/// - an equation `k = v`.
+///
+/// Ids and values are Rust identifiers, which are written as they are, or literals: strings are
+/// quoted and numbers are written as numbers, with an optional `-` in values. Identifiers that are
+/// DOT keywords (`node`, `edge`, `graph`, `digraph`, `subgraph`, `strict`) are rejected at compile
+/// time, write them as strings instead. Attribute pairs are separated by `,` or `;`.
+#[macro_export]
+macro_rules! dot {
+    (strict $kind:ident { $($body:tt)* }) => { $crate::dot!(@graph $kind, true, []; $($body)*) };
+    (strict $kind:ident $id:tt { $($body:tt)* }) => { $crate::dot!(@graph $kind, true, [$id]; $($body)*) };
+    ($kind:ident { $($body:tt)* }) => { $crate::dot!(@graph $kind, false, []; $($body)*) };
+    ($kind:ident $id:tt { $($body:tt)* }) => { $crate::dot!(@graph $kind, false, [$id]; $($body)*) };
+
+    (@graph $kind:ident, $strict:expr, [$($id:tt)?]; $($body:tt)*) => {
+        $crate::GraphBuilder::default()
+            .graph_type($crate::dot!(@kind $kind))
+            .strict($strict)
+            $(.id($crate::dot!(@id $id)))?
+            .stmts($crate::dot!(@stmts $crate::StmtList::new(); []; $($body)*))
+            .build()
+            .unwrap()
+    };
+
+    (@kind graph) => { $crate::GraphType::Graph };
+    (@kind digraph) => { $crate::GraphType::DiGraph };
+    (@kind $other:ident) => { compile_error!(concat!("expected `graph` or `digraph`, found `", stringify!($other), "`")) };
+
+    // collect the tokens of one statement up to its `;`
+    (@stmts $list:expr; [];) => { $list };
+    (@stmts $list:expr; [$($stmt:tt)+];) => { $crate::dot!(@stmt $list; $($stmt)+) };
+    (@stmts $list:expr; [$($stmt:tt)*]; ; $($rest:tt)*) => {
+        $crate::dot!(@stmts $crate::dot!(@stmt $list; $($stmt)*); []; $($rest)*)
+    };
+    (@stmts $list:expr; [$($stmt:tt)*]; $t:tt $($rest:tt)*) => {
+        $crate::dot!(@stmts $list; [$($stmt)* $t]; $($rest)*)
+    };
+
+    (@stmt $list:expr;) => { $list };
+    (@stmt $list:expr; graph [$($attrs:tt)*]) => {
+        $list.add_attr($crate::AttrType::Graph, $crate::dot!(@attrs $crate::AttrList::new(); $($attrs)*))
+    };
+    (@stmt $list:expr; node [$($attrs:tt)*]) => {
+        $list.add_attr($crate::AttrType::Node, $crate::dot!(@attrs $crate::AttrList::new(); $($attrs)*))
+    };
+    (@stmt $list:expr; edge [$($attrs:tt)*]) => {
+        $list.add_attr($crate::AttrType::Edge, $crate::dot!(@attrs $crate::AttrList::new(); $($attrs)*))
+    };
+    (@stmt $list:expr; $k:tt = - $v:literal) => {
+        $list.add_equation($crate::dot!(@id $k), $crate::dot!(@negative $v))
+    };
+    (@stmt $list:expr; $k:tt = $v:tt) => {
+        $list.add_equation($crate::dot!(@id $k), $crate::dot!(@id $v))
+    };
+    (@stmt $list:expr; $a:tt -> $($rest:tt)+) => {
+        $list.add_edge($crate::dot!(@edge $crate::Edge::head_node($crate::dot!(@id $a), None); -> $($rest)+))
+    };
+    (@stmt $list:expr; $a:tt - - $($rest:tt)+) => {
+        $list.add_edge($crate::dot!(@edge $crate::Edge::head_node($crate::dot!(@id $a), None); - - $($rest)+))
+    };
+    (@stmt $list:expr; $a:tt [$($attrs:tt)*]) => {
+        $list.add_node($crate::dot!(@id $a), None, Some($crate::dot!(@attrs $crate::AttrList::new(); $($attrs)*)))
+    };
+    (@stmt $list:expr; $a:tt) => {
+        $list.add_node($crate::dot!(@id $a), None, None)
+    };
+
+    (@edge $edge:expr;) => { $edge };
+    (@edge $edge:expr; [$($attrs:tt)*]) => {
+        $edge.add_attrlist($crate::dot!(@attrs $crate::AttrList::new(); $($attrs)*))
+    };
+    (@edge $edge:expr; -> $b:tt $($rest:tt)*) => {
+        $crate::dot!(@edge $edge.arrow_to_node($crate::dot!(@id $b), None); $($rest)*)
+    };
+    (@edge $edge:expr; - - $b:tt $($rest:tt)*) => {
+        $crate::dot!(@edge $edge.line_to_node($crate::dot!(@id $b), None); $($rest)*)
+    };
+
+    (@attrs $list:expr;) => { $list };
+    (@attrs $list:expr; $k:tt = - $v:literal) => {
+        $list.add($crate::dot!(@id $k), $crate::dot!(@negative $v))
+    };
+    (@attrs $list:expr; $k:tt = - $v:literal , $($rest:tt)*) => {
+        $crate::dot!(@attrs $list.add($crate::dot!(@id $k), $crate::dot!(@negative $v)); $($rest)*)
+    };
+    (@attrs $list:expr; $k:tt = - $v:literal ; $($rest:tt)*) => {
+        $crate::dot!(@attrs $list.add($crate::dot!(@id $k), $crate::dot!(@negative $v)); $($rest)*)
+    };
+    (@attrs $list:expr; $k:tt = $v:tt) => {
+        $list.add($crate::dot!(@id $k), $crate::dot!(@id $v))
+    };
+    (@attrs $list:expr; $k:tt = $v:tt , $($rest:tt)*) => {
+        $crate::dot!(@attrs $list.add($crate::dot!(@id $k), $crate::dot!(@id $v)); $($rest)*)
+    };
+    (@attrs $list:expr; $k:tt = $v:tt ; $($rest:tt)*) => {
+        $crate::dot!(@attrs $list.add($crate::dot!(@id $k), $crate::dot!(@id $v)); $($rest)*)
+    };
+
+    (@id $id:ident) => {{
+        const _: () = assert!(!$crate::macros::is_keyword(stringify!($id)),
+            concat!("`", stringify!($id), "` is a DOT keyword, write it as a string to use it as an id"));
+        $crate::Identity::String(stringify!($id))
+    }};
+    (@negative $v:literal) => { $crate::macros::DotLiteral::into_identity(-$v) };
+    (@id $id:literal) => { $crate::macros::DotLiteral::into_identity($id) };
+}
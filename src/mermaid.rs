@@ -0,0 +1,235 @@
+//! Export graphs as [Mermaid](https://mermaid.js.org/syntax/flowchart.html) flowcharts.
+use std::collections::{HashMap, HashSet};
+
+use crate::graph::endpoint_ids;
+use crate::{AttrList, AttrType, EdgeNode, Graph, GraphType, Identity, Stmt, StmtList, SubGraph};
+
+/// Convert a graph into a Mermaid flowchart, e.g. for embedding it in Markdown.
+///
+/// - the direction follows a top-level `rankdir` (`TB` becomes `TD`) and defaults to `TD`,
+/// - node statements become `id["label"]`, using the `label` attribute or the node id,
+/// - edge chains are split into one `a --> b` (or `a --- b` in undirected graphs) per hop, carrying
+///   the edge `label` as `-->|"label"|`; a subgraph endpoint connects every node in it,
+/// - subgraphs and clusters become `subgraph ... end` blocks titled with their `label`,
+/// - comments become `%%` comments.
+///
+/// Ids that Mermaid cannot use as they are (anything but letters, digits and `_`, or `end`) are
+/// replaced by `_1`, `_2`, ..., skipping any that the graph already uses, and shown with their
+/// original text as label. A subgraph whose id is also a node id gets a made up `subgraph<n>` id
+/// the same way, since Mermaid cannot tell the two apart. Ports, `strict`, and all
+/// other attributes and defaults have no Mermaid counterpart and are left out.
+/// ```
+/// use tabbycat::{to_mermaid, EdgeOp, GraphBuilder, GraphType, Identity, StmtList};
+/// let graph = GraphBuilder::default()
+///     .graph_type(GraphType::DiGraph)
+///     .strict(false)
+///     .stmts(StmtList::new()
+///         .add_edge_between(Identity::id("a").unwrap(), Identity::id("b").unwrap(), EdgeOp::Arrow))
+///     .build()
+///     .unwrap();
+/// assert_eq!("flowchart TD\n    a --> b\n", to_mermaid(&graph));
+/// ```
+pub fn to_mermaid(graph: &Graph) -> String {
+    let direction = graph.stmts.0.iter()
+        .rev()
+        .find_map(|stmt| match stmt {
+            Stmt::Equation(k, v) if k.unquoted() == "rankdir" => Some(v.unquoted()),
+            Stmt::Attr(AttrType::Graph, list) => lookup(list, "rankdir"),
+            _ => None,
+        })
+        .map_or("TD", |rankdir| match rankdir.as_str() {
+            "LR" => "LR",
+            "RL" => "RL",
+            "BT" => "BT",
+            _ => "TD",
+        });
+    let mut exporter = Exporter {
+        arrow: match graph.graph_type {
+            GraphType::Graph => "---",
+            GraphType::DiGraph => "-->",
+        },
+        ids: HashMap::new(),
+        taken: HashSet::new(),
+        nodes: HashSet::new(),
+        generated: 0,
+        labelled: HashSet::new(),
+        subgraphs: 0,
+        out: format!("flowchart {}\n", direction),
+    };
+    exporter.take_ids(&graph.stmts);
+    exporter.list(&graph.stmts, 1);
+    exporter.out
+}
+
+fn lookup(list: &AttrList, key: &str) -> Option<String> {
+    list.to_string_pairs().into_iter()
+        .rev()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v)
+}
+
+/// Whether Mermaid can use `text` as a node id as it is.
+fn is_valid(text: &str) -> bool {
+    !text.is_empty() && text != "end" && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Labels are always quoted, and `"` is the only character that needs an entity code inside quotes.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "#quot;"))
+}
+
+struct Exporter {
+    arrow: &'static str,
+    ids: HashMap<String, String>,
+    /// The ids of the graph that are used as they are, which made up ids must not take
+    taken: HashSet<String>,
+    /// The text of every node id, which subgraphs must not share
+    nodes: HashSet<String>,
+    generated: usize,
+    labelled: HashSet<String>,
+    subgraphs: usize,
+    out: String,
+}
+
+impl Exporter {
+    /// Collect every id in `list` that Mermaid uses as it is, before any id is made up.
+    fn take_ids(&mut self, list: &StmtList) {
+        fn subgraph(exporter: &mut Exporter, sub: &SubGraph) {
+            if let SubGraph::SubGraph { id: Some(id), .. } = sub {
+                exporter.take(id);
+            }
+            exporter.take_ids(sub.stmts());
+        }
+        for stmt in list.0.iter() {
+            match stmt {
+                Stmt::Node { id, .. } => self.take_node(id),
+                Stmt::Edge(edge) => {
+                    for node in std::iter::once(&edge.node).chain(edge.body.iter().map(|body| &body.node)) {
+                        match node {
+                            EdgeNode::Node { id, .. } => self.take_node(id),
+                            EdgeNode::SubGraph(sub) => subgraph(self, sub),
+                        }
+                    }
+                }
+                Stmt::SubGraph(sub) => subgraph(self, sub),
+                Stmt::Attr(..) | Stmt::Equation(..) | Stmt::Comment(..) | Stmt::LineComment(..) => {}
+            }
+        }
+    }
+
+    fn take_node(&mut self, id: &Identity) {
+        self.nodes.insert(id.unquoted());
+        self.take(id);
+    }
+
+    fn take(&mut self, id: &Identity) {
+        let text = id.unquoted();
+        if is_valid(&text) {
+            self.taken.insert(text);
+        }
+    }
+
+    /// The next `<prefix><n>` after `counter` that is not an id of the graph
+    fn fresh(taken: &mut HashSet<String>, prefix: &str, counter: &mut usize) -> String {
+        loop {
+            *counter += 1;
+            let id = format!("{}{}", prefix, counter);
+            if taken.insert(id.clone()) {
+                return id;
+            }
+        }
+    }
+
+    fn list(&mut self, list: &StmtList, depth: usize) {
+        let indent = "    ".repeat(depth);
+        for stmt in list.0.iter() {
+            match stmt {
+                Stmt::Node { id, attr, .. } => {
+                    let text = id.unquoted();
+                    let label = attr.as_ref().and_then(|list| lookup(list, "label")).unwrap_or_else(|| text.clone());
+                    let node = self.node_id(&text);
+                    self.labelled.insert(node.clone());
+                    self.out.push_str(&format!("{}{}[{}]\n", indent, node, quote(&label)));
+                }
+                Stmt::Edge(edge) => {
+                    for node in std::iter::once(&edge.node).chain(edge.body.iter().map(|body| &body.node)) {
+                        if let EdgeNode::SubGraph(sub) = node {
+                            self.subgraph(sub, depth);
+                        }
+                    }
+                    let arrow = match edge.attr.as_ref().and_then(|list| lookup(list, "label")) {
+                        Some(label) => format!("{}|{}|", self.arrow, quote(&label)),
+                        None => self.arrow.to_string(),
+                    };
+                    let mut from = &edge.node;
+                    for body in edge.body.iter() {
+                        let (mut sources, mut targets) = (Vec::new(), Vec::new());
+                        endpoint_ids(from, &mut sources);
+                        endpoint_ids(&body.node, &mut targets);
+                        for source in sources.iter() {
+                            for target in targets.iter() {
+                                let line = format!("{}{} {} {}\n", indent, self.endpoint(source), arrow, self.endpoint(target));
+                                self.out.push_str(&line);
+                            }
+                        }
+                        from = &body.node;
+                    }
+                }
+                Stmt::Comment(text) | Stmt::LineComment(text) => self.out.push_str(&format!("{}%% {}\n", indent, text.replace('\n', " "))),
+                Stmt::SubGraph(sub) => self.subgraph(sub, depth),
+                Stmt::Attr(..) | Stmt::Equation(..) => {}
+            }
+        }
+    }
+
+    fn subgraph(&mut self, sub: &SubGraph, depth: usize) {
+        let indent = "    ".repeat(depth);
+        let (name, node) = match sub {
+            SubGraph::SubGraph { id: Some(id), .. } if self.nodes.contains(&id.unquoted()) =>
+                (id.unquoted(), Self::fresh(&mut self.taken, "subgraph", &mut self.subgraphs)),
+            SubGraph::SubGraph { id: Some(id), .. } => {
+                let name = id.unquoted();
+                let node = self.node_id(&name);
+                (name, node)
+            }
+            _ => {
+                let name = Self::fresh(&mut self.taken, "subgraph", &mut self.subgraphs);
+                (name.clone(), name)
+            }
+        };
+        let title = sub.stmts().0.iter()
+            .rev()
+            .find_map(|stmt| match stmt {
+                Stmt::Equation(k, v) if k.unquoted() == "label" => Some(v.unquoted()),
+                Stmt::Attr(AttrType::Graph, list) => lookup(list, "label"),
+                _ => None,
+            });
+        self.out.push_str(&format!("{}subgraph {}[{}]\n", indent, node, quote(title.as_ref().unwrap_or(&name))));
+        self.list(sub.stmts(), depth + 1);
+        self.out.push_str(&format!("{}end\n", indent));
+    }
+
+    /// The Mermaid id of a node, made up for ids Mermaid cannot take as they are.
+    fn node_id(&mut self, text: &str) -> String {
+        if is_valid(text) {
+            return text.to_string();
+        }
+        if let Some(id) = self.ids.get(text) {
+            return id.clone();
+        }
+        let id = Self::fresh(&mut self.taken, "_", &mut self.generated);
+        self.ids.insert(text.to_string(), id.clone());
+        id
+    }
+
+    /// An edge endpoint, labelled with its original text the first time a made up id is used.
+    fn endpoint(&mut self, id: &Identity) -> String {
+        let text = id.unquoted();
+        let node = self.node_id(&text);
+        if node != text && self.labelled.insert(node.clone()) {
+            format!("{}[{}]", node, quote(&text))
+        } else {
+            node
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use petgraph::graph::IndexType;
+use petgraph::EdgeType;
+
+use crate::{AttrList, Edge, Graph, GraphBuilder, GraphType, Identity, StmtList};
+
+/// Convert a `petgraph::Graph` into a dot graph.
+///
+/// `node` maps each node weight to its identity and optional attributes, `edge` maps each edge
+/// weight to optional attributes. Nodes are emitted as node statements in index order, followed
+/// by one edge statement per edge, using `->` for directed graphs and `--` for undirected ones.
+/// ```
+/// use tabbycat::{from_petgraph, Identity};
+/// let mut g = petgraph::Graph::<&str, ()>::new();
+/// let a = g.add_node("a");
+/// let b = g.add_node("b");
+/// g.add_edge(a, b, ());
+/// let graph = from_petgraph(&g, |n| (Identity::String(n), None), |_| None);
+/// assert_eq!("digraph {a;b;a->b;}", graph.to_string());
+/// ```
+pub fn from_petgraph<'a, N, E, Ty, Ix, FN, FE>(graph: &'a petgraph::Graph<N, E, Ty, Ix>,
+                                              mut node: FN, mut edge: FE) -> Graph<'a>
+    where Ty: EdgeType,
+          Ix: IndexType,
+          FN: FnMut(&'a N) -> (Identity<'a>, Option<AttrList<'a>>),
+          FE: FnMut(&'a E) -> Option<AttrList<'a>> {
+    let mut stmts = StmtList::new();
+    let mut ids = Vec::with_capacity(graph.node_count());
+    for weight in graph.node_weights() {
+        let (id, attr) = node(weight);
+        ids.push(id.clone());
+        stmts = stmts.add_node(id, None, attr);
+    }
+    for e in graph.raw_edges() {
+        let head = Edge::head_node(ids[e.source().index()].clone(), None);
+        let target = ids[e.target().index()].clone();
+        let mut stmt = if graph.is_directed() {
+            head.arrow_to_node(target, None)
+        } else {
+            head.line_to_node(target, None)
+        };
+        if let Some(attr) = edge(&e.weight) {
+            stmt = stmt.add_attrlist(attr);
+        }
+        stmts = stmts.add_edge(stmt);
+    }
+    GraphBuilder::default()
+        .graph_type(if graph.is_directed() { GraphType::DiGraph } else { GraphType::Graph })
+        .strict(false)
+        .stmts(stmts)
+        .build()
+        .unwrap()
+}
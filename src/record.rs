@@ -0,0 +1,86 @@
+use std::fmt::{Formatter, Result};
+
+use crate::Identity;
+
+/// A label of a `shape=record` (or `Mrecord`) node. Fields are separated by `|`, a field may carry
+/// a port name written as `<port>`, and a nested record flips the layout direction:
+/// ```
+/// use tabbycat::{AttrList, Identity, RecordLabel};
+/// let label = RecordLabel::new()
+///     .port_field("f0", "left")
+///     .nested(RecordLabel::new().field("a|b").port_field("f1", "c"))
+///     .field("right");
+/// let attrs = AttrList::new().add(Identity::String("label"), label.into());
+/// assert_eq!(r#"[label="<f0> left|{a\|b|<f1> c}|right"]"#, attrs.to_string());
+/// ```
+/// Literal text is escaped, so `|`, `{`, `}`, `<`, `>`, `"` and `\` appear as they are.
+#[derive(Clone, Debug, Default)]
+pub struct RecordLabel {
+    fields: Vec<RecordField>,
+}
+
+#[derive(Clone, Debug)]
+enum RecordField {
+    Text { port: Option<String>, text: String },
+    Nested(RecordLabel),
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if let '|' | '{' | '}' | '<' | '>' | '"' | '\\' = c {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl RecordLabel {
+    /// Create a record without fields
+    pub fn new() -> Self {
+        RecordLabel { fields: Vec::new() }
+    }
+    /// Add a field showing `text`
+    #[must_use]
+    pub fn field(mut self, text: &str) -> Self {
+        self.fields.push(RecordField::Text { port: None, text: escape(text) });
+        self
+    }
+    /// Add a field showing `text` that edges can connect to with the port `port`
+    #[must_use]
+    pub fn port_field(mut self, port: &str, text: &str) -> Self {
+        self.fields.push(RecordField::Text { port: Some(escape(port)), text: escape(text) });
+        self
+    }
+    /// Add a nested record, written as `{...}`
+    #[must_use]
+    pub fn nested(mut self, record: RecordLabel) -> Self {
+        self.fields.push(RecordField::Nested(record));
+        self
+    }
+}
+
+/// Writes the escaped record text without the surrounding quotes.
+impl std::fmt::Display for RecordLabel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.fields.iter().enumerate().try_for_each(|(i, field)| {
+            if i > 0 {
+                write!(f, "|")
+            } else {
+                Ok(())
+            }.and(match field {
+                RecordField::Text { port: Some(port), text } if text.is_empty() => write!(f, "<{}>", port),
+                RecordField::Text { port: Some(port), text } => write!(f, "<{}> {}", port, text),
+                RecordField::Text { port: None, text } => write!(f, "{}", text),
+                RecordField::Nested(record) => write!(f, "{{{}}}", record),
+            })
+        })
+    }
+}
+
+impl<'a> From<RecordLabel> for Identity<'a> {
+    fn from(record: RecordLabel) -> Self {
+        Identity::EscString(record.to_string())
+    }
+}
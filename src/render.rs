@@ -0,0 +1,67 @@
+//! Render graphs with the graphviz binaries installed on the system.
+use std::io::{Error, ErrorKind, Result, Write};
+use std::process::{Command, Stdio};
+
+use crate::Graph;
+
+/// The graphviz layout engines, passed to `dot` as `-K<engine>`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Engine {
+    Dot,
+    Neato,
+    Fdp,
+    Sfdp,
+    Circo,
+    Twopi,
+}
+
+impl Engine {
+    fn name(self) -> &'static str {
+        match self {
+            Engine::Dot => "dot",
+            Engine::Neato => "neato",
+            Engine::Fdp => "fdp",
+            Engine::Sfdp => "sfdp",
+            Engine::Circo => "circo",
+            Engine::Twopi => "twopi",
+        }
+    }
+}
+
+impl<'a> Graph<'a> {
+    /// Lay out the graph with `engine` and render it in `format` (`svg`, `png`, `pdf`, ... anything
+    /// `dot -T` accepts) by piping it through the `dot` binary, returning the rendered bytes.
+    ///
+    /// When `dot` is not installed the error has the kind `ErrorKind::NotFound`; when graphviz fails,
+    /// e.g. on an unknown format, the error carries its message.
+    pub fn render(&self, engine: Engine, format: &str) -> Result<Vec<u8>> {
+        let mut child = Command::new("dot")
+            .arg(format!("-K{}", engine.name()))
+            .arg(format!("-T{}", format))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|error| match error.kind() {
+                ErrorKind::NotFound =>
+                    Error::new(ErrorKind::NotFound, "the graphviz `dot` binary was not found in PATH"),
+                _ => error,
+            })?;
+        // write from another thread, so a large output cannot block graphviz while it is being fed
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let input = self.to_string();
+        let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+        let output = child.wait_with_output()?;
+        let written = writer.join().expect("the writer thread does not panic");
+        if !output.status.success() {
+            let message = String::from_utf8_lossy(&output.stderr);
+            return Err(Error::other(format!("graphviz failed ({}): {}", output.status, message.trim())));
+        }
+        written.map(|_| output.stdout)
+    }
+
+    /// Render the graph as SVG with the `dot` layout engine, see `Graph::render`.
+    pub fn render_svg(&self) -> Result<Vec<u8>> {
+        self.render(Engine::Dot, "svg")
+    }
+}
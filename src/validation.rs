@@ -0,0 +1,290 @@
+use std::collections::HashSet;
+use std::fmt::{Formatter, Result};
+
+use crate::graph::{id_pattern, is_keyword, numeral_pattern};
+use crate::{AttrList, AttrType, Edge, EdgeNode, EdgeOp, Graph, GraphType, Identity, Port, Stmt, StmtList, SubGraph};
+
+/// A problem found by `Graph::validate`. Offending statements are reported in their rendered form.
+#[derive(Clone, Debug)]
+pub enum ValidationError {
+    /// An edge uses `--` in a digraph or `->` in an undirected graph.
+    EdgeOpMismatch {
+        graph_type: GraphType,
+        edge: String,
+    },
+    /// An edge, or an `edge [...]` default, sets `lhead` or `ltail` while the graph does not set
+    /// `compound=true`, so graphviz ignores them.
+    CompoundRequired {
+        stmt: String,
+    },
+    /// A node with `shape=record` or `shape=Mrecord`, set on the node or by a `node [...]` default,
+    /// has a label without any record fields, ports or nesting, so it is drawn as a single cell.
+    RecordLabelExpected {
+        node: String,
+    },
+    /// An unquoted `Identity::String` or `Identity::Owned` is neither an id (see `Identity::id`),
+    /// a numeral nor an HTML string `<...>` with balanced brackets, or it is a keyword like `node`,
+    /// so it does not read back as a single id.
+    InvalidIdentity {
+        id: String,
+    },
+    /// Several subgraphs share an id, which graphviz merges into a single subgraph. Reported once
+    /// per id.
+    DuplicateSubgraphId {
+        id: String,
+    },
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            ValidationError::EdgeOpMismatch { graph_type: GraphType::DiGraph, edge } =>
+                write!(f, "undirected edge operator `--` in a digraph: {}", edge),
+            ValidationError::EdgeOpMismatch { graph_type: GraphType::Graph, edge } =>
+                write!(f, "directed edge operator `->` in an undirected graph: {}", edge),
+            ValidationError::CompoundRequired { stmt } =>
+                write!(f, "`lhead`/`ltail` without `compound=true` on the graph: {}", stmt),
+            ValidationError::RecordLabelExpected { node } =>
+                write!(f, "record shaped node with a plain label: {}", node),
+            ValidationError::InvalidIdentity { id } =>
+                write!(f, "unquoted identity is not a valid id: {}", id),
+            ValidationError::DuplicateSubgraphId { id } =>
+                write!(f, "subgraph id used more than once: {}", id),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+struct Validator {
+    graph_type: GraphType,
+    compound: bool,
+    subgraph_ids: HashSet<String>,
+    duplicate_ids: HashSet<String>,
+    errors: Vec<ValidationError>,
+}
+
+impl Validator {
+    /// `shape` is the node shape set by the `node [...]` defaults of the enclosing (sub)graphs.
+    fn visit_list(&mut self, list: &StmtList, mut shape: Option<String>) {
+        for stmt in list.0.iter() {
+            match stmt {
+                Stmt::Edge(edge) => self.visit_edge(edge, &shape),
+                Stmt::SubGraph(sub) => self.visit_subgraph(sub, &shape),
+                Stmt::Attr(AttrType::Edge, list) => {
+                    if !self.compound && has_cluster_ends(list.to_string_pairs()) {
+                        self.errors.push(ValidationError::CompoundRequired { stmt: stmt.to_string() });
+                    }
+                }
+                Stmt::Attr(AttrType::Node, list) => {
+                    if let Some(value) = lookup(&list.to_string_pairs(), "shape") {
+                        shape = Some(value);
+                    }
+                }
+                Stmt::Node { attr: Some(list), .. } => {
+                    let pairs = list.to_string_pairs();
+                    let node_shape = lookup(&pairs, "shape").or_else(|| shape.clone());
+                    let record = node_shape.is_some_and(|value| value == "record" || value == "Mrecord");
+                    if record && label_text(list).is_some_and(|label| !is_record_structured(&label)) {
+                        self.errors.push(ValidationError::RecordLabelExpected { node: stmt.to_string() });
+                    }
+                }
+                Stmt::Node { .. } | Stmt::Attr(..) | Stmt::Equation(..) | Stmt::Comment(..) | Stmt::LineComment(..) => {}
+            }
+        }
+    }
+
+    fn visit_edge(&mut self, edge: &Edge, shape: &Option<String>) {
+        let expected = match self.graph_type {
+            GraphType::Graph => EdgeOp::Line,
+            GraphType::DiGraph => EdgeOp::Arrow,
+        };
+        if edge.body.iter().any(|body| body.op != expected) {
+            self.errors.push(ValidationError::EdgeOpMismatch {
+                graph_type: self.graph_type,
+                edge: edge.to_string(),
+            });
+        }
+        if !self.compound && edge.attr.as_ref().is_some_and(|list| has_cluster_ends(list.to_string_pairs())) {
+            self.errors.push(ValidationError::CompoundRequired { stmt: edge.to_string() });
+        }
+        for node in std::iter::once(&edge.node).chain(edge.body.iter().map(|body| &body.node)) {
+            if let EdgeNode::SubGraph(sub) = node {
+                self.visit_subgraph(sub, shape);
+            }
+        }
+    }
+
+    fn visit_subgraph(&mut self, sub: &SubGraph, shape: &Option<String>) {
+        if let SubGraph::SubGraph { id: Some(id), .. } = sub {
+            let id = id.unquoted();
+            if !self.subgraph_ids.insert(id.clone()) && self.duplicate_ids.insert(id.clone()) {
+                self.errors.push(ValidationError::DuplicateSubgraphId { id });
+            }
+        }
+        self.visit_list(sub.stmts(), shape.clone());
+    }
+}
+
+/// The value of the last pair with `key`, which is the one graphviz uses.
+fn lookup(pairs: &[(String, String)], key: &str) -> Option<String> {
+    pairs.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v.clone())
+}
+
+/// The last label of `list` as graphviz reads it: the rendered value without its surrounding
+/// quotes and with escaped quotes unescaped, so the escaping of `Identity` has been applied.
+fn label_text(list: &AttrList) -> Option<String> {
+    list.0.iter()
+        .flatten()
+        .rev()
+        .find(|(k, _)| k.unquoted() == "label")
+        .map(|(_, v)| {
+            let rendered = v.to_string();
+            match rendered.strip_prefix('"').and_then(|text| text.strip_suffix('"')) {
+                Some(inner) => inner.replace("\\\"", "\""),
+                None => rendered,
+            }
+        })
+}
+
+/// Whether a record label has more than a single plain field: an unescaped `|` between fields,
+/// `{` around nested fields or `<` before a port name.
+fn is_record_structured(label: &str) -> bool {
+    let mut escaped = false;
+    label.chars().any(|c| {
+        let special = !escaped && matches!(c, '|' | '{' | '<');
+        escaped = !escaped && c == '\\';
+        special
+    })
+}
+
+fn has_cluster_ends(pairs: Vec<(String, String)>) -> bool {
+    pairs.iter().any(|(k, _)| k == "lhead" || k == "ltail")
+}
+
+/// Whether a boolean attribute value reads as true to graphviz: `true` or `yes` in any case, or a
+/// nonzero integer.
+fn is_true(value: &str) -> bool {
+    value.eq_ignore_ascii_case("true")
+        || value.eq_ignore_ascii_case("yes")
+        || value.parse::<i64>().is_ok_and(|number| number != 0)
+}
+
+/// Whether the top level statements set `compound` to true; later settings override earlier ones.
+fn sets_compound(list: &StmtList) -> bool {
+    list.0.iter()
+        .rev()
+        .find_map(|stmt| match stmt {
+            Stmt::Equation(k, v) if k.unquoted() == "compound" => Some(v.unquoted()),
+            Stmt::Attr(AttrType::Graph, list) => list.to_string_pairs().into_iter()
+                .rev()
+                .find(|(k, _)| k == "compound")
+                .map(|(_, v)| v),
+            _ => None,
+        })
+        .is_some_and(|value| is_true(&value))
+}
+
+/// Report an unquoted string identity that does not render as a single id.
+fn check(id: &Identity, errors: &mut Vec<ValidationError>) {
+    let text = match id {
+        Identity::String(text) => *text,
+        Identity::Owned(text) => text.as_str(),
+        _ => return,
+    };
+    let id = id_pattern().is_match(text) && !is_keyword(text);
+    if !(id || numeral_pattern().is_match(text) || is_html(text)) {
+        errors.push(ValidationError::InvalidIdentity { id: text.to_string() });
+    }
+}
+
+/// Whether `text` is an HTML string: `<` and `>` nest, and the first `<` closes at the last `>`.
+fn is_html(text: &str) -> bool {
+    let mut depth = 0usize;
+    text.starts_with('<') && text.char_indices().all(|(i, c)| {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            _ => {}
+        }
+        let last = i + c.len_utf8() == text.len();
+        (depth == 0) == last
+    })
+}
+
+/// Report every unquoted string identity in `list`, subgraphs included, see `check`.
+fn check_ids(list: &StmtList, errors: &mut Vec<ValidationError>) {
+    fn check_port(port: &Option<Port>, errors: &mut Vec<ValidationError>) {
+        if let Some(Port::ID(id, _)) = port {
+            check(id, errors);
+        }
+    }
+    fn check_attrs(list: &AttrList, errors: &mut Vec<ValidationError>) {
+        for (k, v) in list.0.iter().flatten() {
+            check(k, errors);
+            check(v, errors);
+        }
+    }
+    fn check_subgraph(sub: &SubGraph, errors: &mut Vec<ValidationError>) {
+        if let SubGraph::SubGraph { id: Some(id), .. } = sub {
+            check(id, errors);
+        }
+        check_ids(sub.stmts(), errors);
+    }
+    for stmt in list.0.iter() {
+        match stmt {
+            Stmt::Node { id, port, attr } => {
+                check(id, errors);
+                check_port(port, errors);
+                if let Some(list) = attr {
+                    check_attrs(list, errors);
+                }
+            }
+            Stmt::Edge(edge) => {
+                for node in std::iter::once(&edge.node).chain(edge.body.iter().map(|body| &body.node)) {
+                    match node {
+                        EdgeNode::Node { id, port } => {
+                            check(id, errors);
+                            check_port(port, errors);
+                        }
+                        EdgeNode::SubGraph(sub) => check_subgraph(sub, errors),
+                    }
+                }
+                if let Some(list) = &edge.attr {
+                    check_attrs(list, errors);
+                }
+            }
+            Stmt::Attr(_, list) => check_attrs(list, errors),
+            Stmt::Equation(k, v) => {
+                check(k, errors);
+                check(v, errors);
+            }
+            Stmt::SubGraph(sub) => check_subgraph(sub, errors),
+            Stmt::Comment(..) | Stmt::LineComment(..) => {}
+        }
+    }
+}
+
+impl<'a> Graph<'a> {
+    /// Check the graph for mistakes that would make graphviz reject or misread the output,
+    /// returning every problem found. Subgraphs, including subgraph edge endpoints, are checked too.
+    pub fn validate(&self) -> std::result::Result<(), Vec<ValidationError>> {
+        let mut validator = Validator {
+            graph_type: self.graph_type,
+            compound: sets_compound(&self.stmts),
+            subgraph_ids: HashSet::new(),
+            duplicate_ids: HashSet::new(),
+            errors: Vec::new(),
+        };
+        validator.visit_list(&self.stmts, None);
+        if let Some(id) = &self.id {
+            check(id, &mut validator.errors);
+        }
+        check_ids(&self.stmts, &mut validator.errors);
+        if validator.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(validator.errors)
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Transform graphs in place by walking their statements with a `GraphVisitor`.
+//!
+//! Every `visit_*` method of the trait defaults to the `walk_*` function of the same name, which
+//! visits the parts of the item; an overriding method calls it to keep going deeper.
+use std::borrow::Cow;
+
+use crate::{AttrList, AttrType, Edge, EdgeNode, Graph, Identity, Port, Stmt, StmtList, SubGraph};
+
+/// A mutable visitor over the statements of a graph, driven by `Graph::accept`. Node ids are seen
+/// by `visit_node_id`, both in node statements and as edge endpoints:
+/// ```
+/// use tabbycat::{Edge, GraphBuilder, GraphType, GraphVisitor, Identity, StmtList};
+/// struct Prefix;
+/// impl<'a> GraphVisitor<'a> for Prefix {
+///     fn visit_node_id(&mut self, id: &mut Identity<'a>) {
+///         *id = Identity::Owned(format!("n_{}", id));
+///     }
+/// }
+/// let mut graph = GraphBuilder::default()
+///     .graph_type(GraphType::DiGraph)
+///     .strict(false)
+///     .stmts(StmtList::new()
+///         .add_edge(Edge::head_node(Identity::id("a").unwrap(), None)
+///             .arrow_to_node(Identity::id("b").unwrap(), None)))
+///     .build()
+///     .unwrap();
+/// graph.accept(&mut Prefix);
+/// assert_eq!("digraph {n_a->n_b;}", graph.to_string());
+/// ```
+pub trait GraphVisitor<'a> {
+    /// A list of statements, the graph body or a subgraph body.
+    fn visit_stmt_list(&mut self, list: &mut StmtList<'a>) {
+        walk_stmt_list(self, list)
+    }
+    /// A single statement of any kind.
+    fn visit_stmt(&mut self, stmt: &mut Stmt<'a>) {
+        walk_stmt(self, stmt)
+    }
+    /// An edge statement, its endpoints and attribute list.
+    fn visit_edge(&mut self, edge: &mut Edge<'a>) {
+        walk_edge(self, edge)
+    }
+    /// An edge endpoint, a node with its port or a subgraph.
+    fn visit_edge_node(&mut self, node: &mut EdgeNode<'a>) {
+        walk_edge_node(self, node)
+    }
+    /// A subgraph statement or endpoint, its id and statements.
+    fn visit_subgraph(&mut self, sub: &mut SubGraph<'a>) {
+        walk_subgraph(self, sub)
+    }
+    /// A `node [...]`, `edge [...]` or `graph [...]` statement, its list is visited by default.
+    fn visit_attr_stmt(&mut self, _attr_type: AttrType, list: &mut AttrList<'a>) {
+        self.visit_attr_list(list)
+    }
+    /// The attribute list of a node, an edge or an attribute statement.
+    fn visit_attr_list(&mut self, _list: &mut AttrList<'a>) {}
+    /// The id of a node statement or of a node endpoint.
+    fn visit_node_id(&mut self, _id: &mut Identity<'a>) {}
+    /// The id of a named subgraph.
+    fn visit_subgraph_id(&mut self, _id: &mut Identity<'a>) {}
+    /// The port suffix of a node statement or a node endpoint.
+    fn visit_port(&mut self, _port: &mut Port<'a>) {}
+    /// A `key=value` statement.
+    fn visit_equation(&mut self, _key: &mut Identity<'a>, _value: &mut Identity<'a>) {}
+    /// Both `/* ... */` and `// ...` comments.
+    fn visit_comment(&mut self, _text: &mut Cow<'a, str>) {}
+}
+
+/// Visit every statement of `list`.
+pub fn walk_stmt_list<'a, V: GraphVisitor<'a> + ?Sized>(visitor: &mut V, list: &mut StmtList<'a>) {
+    for stmt in list.0.iter_mut() {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+/// Visit the parts of `stmt` with the visitor method for its kind.
+pub fn walk_stmt<'a, V: GraphVisitor<'a> + ?Sized>(visitor: &mut V, stmt: &mut Stmt<'a>) {
+    match stmt {
+        Stmt::Edge(edge) => visitor.visit_edge(edge),
+        Stmt::Node { id, port, attr } => {
+            visitor.visit_node_id(id);
+            if let Some(port) = port {
+                visitor.visit_port(port);
+            }
+            if let Some(list) = attr {
+                visitor.visit_attr_list(list);
+            }
+        }
+        Stmt::Attr(attr_type, list) => visitor.visit_attr_stmt(*attr_type, list),
+        Stmt::Equation(key, value) => visitor.visit_equation(key, value),
+        Stmt::Comment(text) | Stmt::LineComment(text) => visitor.visit_comment(text),
+        Stmt::SubGraph(sub) => visitor.visit_subgraph(sub),
+    }
+}
+
+/// Visit the endpoints of `edge`, then its attribute list.
+pub fn walk_edge<'a, V: GraphVisitor<'a> + ?Sized>(visitor: &mut V, edge: &mut Edge<'a>) {
+    visitor.visit_edge_node(&mut edge.node);
+    for body in edge.body.iter_mut() {
+        visitor.visit_edge_node(&mut body.node);
+    }
+    if let Some(list) = &mut edge.attr {
+        visitor.visit_attr_list(list);
+    }
+}
+
+/// Visit the id and port of a node endpoint, or the subgraph endpoint.
+pub fn walk_edge_node<'a, V: GraphVisitor<'a> + ?Sized>(visitor: &mut V, node: &mut EdgeNode<'a>) {
+    match node {
+        EdgeNode::Node { id, port } => {
+            visitor.visit_node_id(id);
+            if let Some(port) = port {
+                visitor.visit_port(port);
+            }
+        }
+        EdgeNode::SubGraph(sub) => visitor.visit_subgraph(sub),
+    }
+}
+
+/// Visit the id of `sub` if it has one, then its statements.
+pub fn walk_subgraph<'a, V: GraphVisitor<'a> + ?Sized>(visitor: &mut V, sub: &mut SubGraph<'a>) {
+    if let SubGraph::SubGraph { id: Some(id), .. } = sub {
+        visitor.visit_subgraph_id(id);
+    }
+    visitor.visit_stmt_list(sub.stmts_mut());
+}
+
+impl<'a> Graph<'a> {
+    /// Walk all statements of the graph, subgraphs included, with `visitor`.
+    pub fn accept<V: GraphVisitor<'a>>(&mut self, visitor: &mut V) {
+        visitor.visit_stmt_list(&mut self.stmts)
+    }
+}
@@ -0,0 +1,7 @@
+#[cfg(feature = "macros")]
+#[test]
+fn dot_macro_ui() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/dot-pass.rs");
+    t.compile_fail("tests/ui/dot-*-fail.rs");
+}
@@ -0,0 +1,38 @@
+#![cfg(feature = "render")]
+use std::io::ErrorKind;
+use std::process::Command;
+
+use tabbycat::{Engine, Graph, GraphType, Identity};
+
+fn graphviz_installed() -> bool {
+    Command::new("dot").arg("-V").output().is_ok_and(|output| output.status.success())
+}
+
+fn graph() -> Graph<'static> {
+    Graph::from_edges(GraphType::DiGraph, vec![
+        (Identity::id("a").unwrap(), Identity::id("b").unwrap()),
+    ])
+}
+
+#[test]
+fn render_without_graphviz() {
+    if graphviz_installed() {
+        return;
+    }
+    assert_eq!(ErrorKind::NotFound, graph().render_svg().unwrap_err().kind());
+}
+
+#[test]
+fn render_with_graphviz() {
+    if !graphviz_installed() {
+        eprintln!("graphviz is not installed, skipping");
+        return;
+    }
+    let graph = graph();
+    let svg = String::from_utf8(graph.render_svg().unwrap()).unwrap();
+    assert!(svg.contains("<svg"));
+    assert!(svg.contains("<title>a&#45;&gt;b</title>"));
+    let plain = String::from_utf8(graph.render(Engine::Neato, "plain").unwrap()).unwrap();
+    assert!(plain.starts_with("graph "));
+    assert!(graph.render(Engine::Dot, "no-such-format").is_err());
+}
@@ -0,0 +1,5 @@
+use tabbycat::dot;
+
+fn main() {
+    let _ = dot!(digraph { node; });
+}
@@ -0,0 +1,13 @@
+use tabbycat::dot;
+
+fn main() {
+    let graph = dot!(strict graph G {
+        rankdir = LR;
+        node [shape = box];
+        a -- b -- c [label = "path"];
+        d;
+    });
+    assert_eq!(
+        "strict graph G {rankdir=LR;node [shape=box];a--b--c[label=\"path\"];d;}",
+        graph.to_string());
+}